@@ -0,0 +1,533 @@
+// This is free and unencumbered software released into the public domain.
+
+//! A content-addressed store for downloaded release artifacts.
+//!
+//! Artifacts are stored under `~/.asimov/cache/sha256/<hash>`, and the URLs
+//! they were downloaded from are recorded under `~/.asimov/cache/refs/`.
+//! Interrupted downloads are kept under `~/.asimov/cache/partial/` and are
+//! resumed with HTTP range requests on the next attempt, with a lock file
+//! alongside so that concurrent downloads of the same URL take turns.
+
+use asimov_env::paths::asimov_root;
+use reqwest::{StatusCode, header};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("HTTP status code was not successful: {0}")]
+    NotSuccess(StatusCode),
+    #[error("failed to access the download cache: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid checksum `{0}`, expected `{1}`")]
+    InvalidChecksum(String, String),
+}
+
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// The SHA-256 hash of the content, or `None` for a partial download.
+    pub hash: Option<String>,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// The URLs that the content was downloaded from.
+    pub urls: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new(asimov_root().join("cache"))
+    }
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn blobs_dir(&self) -> PathBuf {
+        self.dir.join("sha256")
+    }
+
+    fn refs_dir(&self) -> PathBuf {
+        self.dir.join("refs")
+    }
+
+    fn partial_dir(&self) -> PathBuf {
+        self.dir.join("partial")
+    }
+
+    /// Returns the path of the cached content with the given SHA-256 hash, if
+    /// present.
+    pub async fn get(&self, hash: &str) -> Option<PathBuf> {
+        let path = self.blobs_dir().join(hash.to_ascii_lowercase());
+        tokio::fs::try_exists(&path)
+            .await
+            .unwrap_or(false)
+            .then_some(path)
+    }
+
+    /// Looks up previously downloaded content, either by its expected
+    /// checksum or, lacking one, by the URL it was downloaded from.
+    pub async fn lookup(&self, url: &str, checksum: Option<&str>) -> Option<PathBuf> {
+        let hash = match checksum.map(parse_checksum) {
+            Some(hash) => hash,
+            None => {
+                let reference = tokio::fs::read_to_string(self.refs_dir().join(hash_str(url)))
+                    .await
+                    .ok()?;
+                reference.split_whitespace().next()?.to_string()
+            },
+        };
+
+        let path = self.get(&hash).await?;
+
+        // Mark the content as recently used, for `cache prune`:
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .inspect_err(|err| tracing::debug!(?path, ?err, "failed to touch cached file"))
+            .ok();
+
+        Some(path)
+    }
+
+    /// Returns the path of the content at the given URL, downloading it into
+    /// the cache unless it's already present there. Returns `None` if the
+    /// server responds with `404 Not Found`.
+    ///
+    /// If a previous download of the same URL was interrupted, the download
+    /// resumes from where it left off.
+    pub async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        checksum: Option<&str>,
+    ) -> Result<Option<PathBuf>, CacheError> {
+        let expected = checksum.map(parse_checksum);
+        if let Some(path) = self.verified_lookup(url, expected.as_deref()).await? {
+            return Ok(Some(path));
+        }
+
+        let key = hash_str(url);
+        tokio::fs::create_dir_all(self.partial_dir()).await?;
+        let partial_path = self.partial_dir().join(&key);
+
+        // Held until the download is committed, and released on drop:
+        let _lock = lock_file(self.partial_dir().join(format!("{key}.lock"))).await?;
+
+        // Another download of the same URL may have completed meanwhile:
+        if let Some(path) = self.verified_lookup(url, expected.as_deref()).await? {
+            return Ok(Some(path));
+        }
+
+        let offset = tokio::fs::metadata(&partial_path)
+            .await
+            .map(|md| md.len())
+            .unwrap_or(0);
+
+        let mut response = send(client, url, offset).await?;
+        let mut file = match response.status() {
+            StatusCode::NOT_FOUND => return Ok(None),
+            StatusCode::PARTIAL_CONTENT if offset > 0 => {
+                tracing::debug!(url, offset, "resuming interrupted download");
+                tokio::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&partial_path)
+                    .await?
+            },
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                // The partial download may be complete, or stale, and there's
+                // no telling which, so start over:
+                tracing::debug!(url, offset, "discarding partial download");
+                response = send(client, url, 0).await?;
+                match response.status() {
+                    StatusCode::NOT_FOUND => return Ok(None),
+                    status if status.is_success() => tokio::fs::File::create(&partial_path).await?,
+                    status => return Err(CacheError::NotSuccess(status)),
+                }
+            },
+            status if status.is_success() => tokio::fs::File::create(&partial_path).await?,
+            status => return Err(CacheError::NotSuccess(status)),
+        };
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .inspect_err(|err| tracing::debug!(?err))?
+        {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        let hash = hash_file(&partial_path).await?;
+
+        if let Some(expected) = expected
+            && hash != expected
+        {
+            tokio::fs::remove_file(&partial_path).await?;
+            return Err(CacheError::InvalidChecksum(hash, expected));
+        }
+
+        tokio::fs::create_dir_all(self.blobs_dir()).await?;
+        let blob_path = self.blobs_dir().join(&hash);
+        tokio::fs::rename(&partial_path, &blob_path).await?;
+
+        tokio::fs::create_dir_all(self.refs_dir()).await?;
+        tokio::fs::write(self.refs_dir().join(&key), format!("{hash}  {url}\n")).await?;
+
+        Ok(Some(blob_path))
+    }
+
+    /// Looks up previously downloaded content like [`Cache::lookup`], also
+    /// checking its hash when the checksum is known. Corrupted content is
+    /// removed, to be downloaded again.
+    async fn verified_lookup(
+        &self,
+        url: &str,
+        expected: Option<&str>,
+    ) -> Result<Option<PathBuf>, CacheError> {
+        let Some(path) = self.lookup(url, expected).await else {
+            return Ok(None);
+        };
+        if let Some(expected) = expected
+            && hash_file(&path).await? != expected
+        {
+            tracing::debug!(url, ?path, "removing corrupted download from cache");
+            tokio::fs::remove_file(&path).await?;
+            return Ok(None);
+        }
+        tracing::debug!(url, ?path, "found download in cache");
+        Ok(Some(path))
+    }
+
+    /// Returns all the cached content, including partial downloads.
+    pub async fn entries(&self) -> Result<Vec<CacheEntry>, CacheError> {
+        let mut urls: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (_, path) in read_dir(&self.refs_dir()).await? {
+            let Ok(reference) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if let Some((hash, url)) = reference.trim().split_once("  ") {
+                urls.entry(hash.into()).or_default().push(url.into());
+            }
+        }
+
+        let mut entries = Vec::new();
+        for (name, path) in read_dir(&self.blobs_dir()).await? {
+            let md = tokio::fs::metadata(&path).await?;
+            entries.push(CacheEntry {
+                urls: urls.remove(&name).unwrap_or_default(),
+                hash: Some(name),
+                path,
+                size: md.len(),
+                modified: md.modified().ok(),
+            });
+        }
+        for (name, path) in read_dir(&self.partial_dir()).await? {
+            if name.ends_with(".lock") {
+                continue;
+            }
+            let md = tokio::fs::metadata(&path).await?;
+            entries.push(CacheEntry {
+                hash: None,
+                path,
+                size: md.len(),
+                modified: md.modified().ok(),
+                urls: Vec::new(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Removes cached content (and partial downloads) that hasn't been used
+    /// within the given duration, returning the removed entries.
+    pub async fn prune(&self, max_age: Duration) -> Result<Vec<CacheEntry>, CacheError> {
+        let now = SystemTime::now();
+
+        let mut removed = Vec::new();
+        for entry in self.entries().await? {
+            let age = entry
+                .modified
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if age >= max_age {
+                tokio::fs::remove_file(&entry.path).await?;
+                removed.push(entry);
+            }
+        }
+
+        // Remove references to content which is no longer present:
+        for (_, path) in read_dir(&self.refs_dir()).await? {
+            let reference = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+            let hash = reference.split_whitespace().next().unwrap_or_default();
+            if hash.is_empty() || self.get(hash).await.is_none() {
+                tokio::fs::remove_file(&path).await?;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes the cached content downloaded from URLs matching the
+    /// predicate, returning the removed entries. Content that was also
+    /// downloaded from other URLs is kept.
+    pub async fn remove_matching(
+        &self,
+        predicate: impl Fn(&str) -> bool,
+    ) -> Result<Vec<CacheEntry>, CacheError> {
+        let mut removed = Vec::new();
+        for entry in self.entries().await? {
+            if entry.urls.is_empty() || !entry.urls.iter().all(|url| predicate(url)) {
+                continue;
+            }
+            tokio::fs::remove_file(&entry.path).await?;
+            for url in &entry.urls {
+                let path = self.refs_dir().join(hash_str(url));
+                tokio::fs::remove_file(&path).await.or_else(|e| {
+                    if e.kind() == io::ErrorKind::NotFound {
+                        Ok(())
+                    } else {
+                        Err(e)
+                    }
+                })?;
+            }
+            removed.push(entry);
+        }
+        Ok(removed)
+    }
+
+    /// Removes all cached content, returning the removed entries.
+    pub async fn clear(&self) -> Result<Vec<CacheEntry>, CacheError> {
+        let entries = self.entries().await?;
+        for dir in [self.blobs_dir(), self.refs_dir(), self.partial_dir()] {
+            tokio::fs::remove_dir_all(&dir).await.or_else(|e| {
+                if e.kind() == io::ErrorKind::NotFound {
+                    Ok(())
+                } else {
+                    Err(e)
+                }
+            })?;
+        }
+        Ok(entries)
+    }
+}
+
+/// Extracts just the hash from a checksum file's contents, which may also
+/// contain a file name.
+fn parse_checksum(checksum: &str) -> String {
+    checksum
+        .split_whitespace()
+        .next()
+        .unwrap_or(checksum)
+        .to_ascii_lowercase()
+}
+
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    offset: u64,
+) -> Result<reqwest::Response, CacheError> {
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(header::RANGE, format!("bytes={offset}-"));
+    }
+    Ok(request
+        .send()
+        .await
+        .inspect_err(|err| tracing::debug!(?err))?)
+}
+
+/// Opens the lock file and waits for an exclusive lock on it.
+async fn lock_file(path: PathBuf) -> io::Result<std::fs::File> {
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        file.lock()?;
+        Ok(file)
+    })
+    .await?
+}
+
+fn hash_str(input: &str) -> String {
+    format!("{:x}", Sha256::digest(input.as_bytes()))
+}
+
+async fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file = tokio::fs::File::open(path).await?;
+
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Lists the files in a directory, treating a missing directory as empty.
+async fn read_dir(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut files = Vec::new();
+    while let Some(entry) = read_dir.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            files.push((
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path(),
+            ));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checksum() {
+        assert_eq!(parse_checksum("ABCDEF"), "abcdef");
+        assert_eq!(
+            parse_checksum("abcdef  asimov-foo-module-linux-x86-gnu.tar.gz\n"),
+            "abcdef"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_and_prune() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let cache = Cache::new(dir.path());
+
+        let hash = hash_str("hello");
+        tokio::fs::create_dir_all(cache.blobs_dir()).await.unwrap();
+        tokio::fs::write(cache.blobs_dir().join(&hash), "hello")
+            .await
+            .unwrap();
+
+        assert!(
+            cache
+                .lookup("https://example.org/", Some(&hash))
+                .await
+                .is_some()
+        );
+        assert!(cache.lookup("https://example.org/", None).await.is_none());
+
+        assert!(
+            cache
+                .prune(Duration::from_secs(60))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(cache.prune(Duration::ZERO).await.unwrap().len(), 1);
+        assert!(cache.get(&hash).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verified_lookup() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let cache = Cache::new(dir.path());
+
+        tokio::fs::create_dir_all(cache.blobs_dir()).await.unwrap();
+        let (good, bad) = (hash_str("hello"), hash_str("world"));
+        tokio::fs::write(cache.blobs_dir().join(&good), "hello")
+            .await
+            .unwrap();
+        tokio::fs::write(cache.blobs_dir().join(&bad), "truncated")
+            .await
+            .unwrap();
+
+        let url = "https://example.org/";
+        assert!(
+            cache
+                .verified_lookup(url, Some(&good))
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            cache
+                .verified_lookup(url, Some(&bad))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(cache.get(&bad).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_remove_matching() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let cache = Cache::new(dir.path());
+
+        tokio::fs::create_dir_all(cache.blobs_dir()).await.unwrap();
+        tokio::fs::create_dir_all(cache.refs_dir()).await.unwrap();
+        for (content, url) in [
+            ("foo", "https://example.org/foo"),
+            ("bar", "https://example.org/bar"),
+        ] {
+            let hash = hash_str(content);
+            tokio::fs::write(cache.blobs_dir().join(&hash), content)
+                .await
+                .unwrap();
+            tokio::fs::write(
+                cache.refs_dir().join(hash_str(url)),
+                format!("{hash}  {url}\n"),
+            )
+            .await
+            .unwrap();
+        }
+
+        let removed = cache
+            .remove_matching(|url| url.ends_with("/foo"))
+            .await
+            .unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(cache.get(&hash_str("foo")).await.is_none());
+        assert!(
+            cache
+                .lookup("https://example.org/bar", None)
+                .await
+                .is_some()
+        );
+        assert!(
+            cache
+                .lookup("https://example.org/foo", None)
+                .await
+                .is_none()
+        );
+    }
+}
@@ -2,18 +2,36 @@
 
 #![allow(unused)]
 
+mod autoremove;
+pub use autoremove::*;
+
 mod browse;
 pub use browse::*;
 
+mod cache;
+pub use cache::*;
+
+mod changelog;
+pub use changelog::*;
+
 mod config;
 pub use config::*;
 
 mod disable;
 pub use disable::*;
 
+mod doctor;
+pub use doctor::*;
+
+mod download;
+pub use download::*;
+
 mod enable;
 pub use enable::*;
 
+mod exec;
+pub use exec::*;
+
 mod find;
 pub use find::*;
 
@@ -29,9 +47,36 @@ pub use link::*;
 mod list;
 pub use list::*;
 
+mod mark;
+pub use mark::*;
+
+mod models;
+pub use models::*;
+
+mod outdated;
+pub use outdated::*;
+
+mod pin;
+pub use pin::*;
+
+mod profile;
+pub use profile::*;
+
+mod reinstall;
+pub use reinstall::*;
+
 mod resolve;
 pub use resolve::*;
 
+mod rollback;
+pub use rollback::*;
+
+mod self_update;
+pub use self_update::*;
+
+mod settings;
+pub use settings::*;
+
 mod uninstall;
 pub use uninstall::*;
 
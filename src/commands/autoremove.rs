@@ -0,0 +1,96 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    dependencies::Dependencies,
+    reasons::{InstallReason, InstallReasons},
+};
+use color_print::cprintln;
+
+#[tokio::main]
+pub async fn autoremove(dry_run: bool, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+    let installer = crate::installer::Installer::default();
+    let reasons = InstallReasons::default();
+
+    let installed = registry.installed_modules().await.map_err(|e| {
+        tracing::error!("failed to read installed modules: {e}");
+        EX_UNAVAILABLE
+    })?;
+    let deps = Dependencies::from_manifests(&installed);
+
+    let mut auto_installed = Vec::new();
+    for manifest in &installed {
+        let module_name = &manifest.manifest.name;
+        let reason = reasons.get(module_name).await.map_err(|e| {
+            tracing::error!("failed to read install reason of `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })?;
+        if reason == InstallReason::Auto {
+            auto_installed.push(module_name.clone());
+        }
+    }
+
+    // Removing a module may leave its own dependencies unrequired, so repeat
+    // until nothing more can be removed:
+    let mut unrequired: Vec<String> = Vec::new();
+    loop {
+        let before = unrequired.len();
+        for module_name in &auto_installed {
+            if !unrequired.contains(module_name)
+                && deps
+                    .dependents(module_name)
+                    .iter()
+                    .all(|dependent| unrequired.contains(dependent))
+            {
+                unrequired.push(module_name.clone());
+            }
+        }
+        if unrequired.len() == before {
+            break;
+        }
+    }
+
+    if unrequired.is_empty() {
+        if flags.verbose > 0 {
+            cprintln!("<s,g>✓</> No modules to remove.");
+        }
+        return Ok(());
+    }
+
+    let unrequired = deps.removal_order(&unrequired);
+    cprintln!(
+        "<s,y>-</> Modules no longer required: <s>{}</>.",
+        unrequired.join(", ")
+    );
+    if dry_run {
+        return Ok(());
+    }
+
+    for module_name in unrequired {
+        if flags.verbose > 1 {
+            cprintln!("<s,c>»</> Uninstalling the module <s>{module_name}</>...");
+        }
+
+        installer
+            .uninstall_module(&module_name)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to uninstall module `{module_name}`: {e}");
+                EX_UNAVAILABLE
+            })?;
+
+        reasons
+            .remove(&module_name)
+            .await
+            .inspect_err(|e| tracing::warn!("failed to remove install reason: {e}"))
+            .ok();
+
+        if flags.verbose > 0 {
+            cprintln!("<s,g>✓</> Uninstalled the module <s>{module_name}</>.");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    cache::{Cache, CacheEntry},
+    format_bytes,
+};
+use color_print::cprintln;
+use std::time::Duration;
+
+#[tokio::main]
+pub async fn cache_list(_flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let entries = Cache::default().entries().await.map_err(|e| {
+        tracing::error!("failed to read download cache: {e}");
+        EX_UNAVAILABLE
+    })?;
+
+    for entry in &entries {
+        let hash = match entry.hash {
+            Some(ref hash) => hash.get(..12).unwrap_or(hash),
+            None => "(partial)",
+        };
+        let url = entry.urls.first().map(String::as_str).unwrap_or_default();
+        cprintln!("<s>{hash:<12}</> {:>10}  {url}", format_bytes(entry.size));
+    }
+
+    let total: u64 = entries.iter().map(|entry| entry.size).sum();
+    cprintln!(
+        "<dim>{} entries, {} total</>",
+        entries.len(),
+        format_bytes(total)
+    );
+
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn cache_prune(max_age_days: u64, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let max_age = Duration::from_secs(max_age_days.saturating_mul(24 * 60 * 60));
+    let removed = Cache::default().prune(max_age).await.map_err(|e| {
+        tracing::error!("failed to prune download cache: {e}");
+        EX_UNAVAILABLE
+    })?;
+
+    print_removed(&removed, flags);
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn cache_clear(flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let removed = Cache::default().clear().await.map_err(|e| {
+        tracing::error!("failed to clear download cache: {e}");
+        EX_UNAVAILABLE
+    })?;
+
+    print_removed(&removed, flags);
+    Ok(())
+}
+
+fn print_removed(removed: &[CacheEntry], flags: &StandardOptions) {
+    if flags.verbose > 0 {
+        for entry in removed {
+            cprintln!("<s,c>»</> Removed <s>{}</>.", entry.path.display());
+        }
+    }
+
+    let total: u64 = removed.iter().map(|entry| entry.size).sum();
+    cprintln!(
+        "<s,g>✓</> Removed {} cache entries, freeing {}.",
+        removed.len(),
+        format_bytes(total)
+    );
+}
@@ -0,0 +1,84 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    installer::Installer,
+    policy::in_range,
+};
+use color_print::{ceprintln, cprintln};
+
+#[tokio::main]
+pub async fn changelog(
+    module_name: String,
+    from: Option<String>,
+    to: Option<String>,
+    _flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let from = match from {
+        Some(from) => Some(from),
+        None => asimov_registry::Registry::default()
+            .module_version(&module_name)
+            .await
+            .ok()
+            .flatten(),
+    };
+
+    print_changes(
+        &Installer::default(),
+        &module_name,
+        from.as_deref(),
+        to.as_deref(),
+    )
+    .await
+}
+
+/// Prints the release notes of the module's releases newer than `from` up to
+/// and including `to`, newest first.
+pub(crate) async fn print_changes(
+    installer: &Installer,
+    module_name: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(), SysexitsError> {
+    let releases = installer.fetch_releases(module_name).await.map_err(|e| {
+        tracing::error!("unable to fetch releases of module `{module_name}`: {e}");
+        EX_UNAVAILABLE
+    })?;
+
+    let releases: Vec<_> = releases
+        .into_iter()
+        .filter(|release| in_range(&release.version, from, to))
+        .collect();
+
+    if releases.is_empty() {
+        ceprintln!("<s,y>warn:</> No releases of module <s>{module_name}</> found in the range.");
+        return Ok(());
+    }
+
+    for release in releases {
+        let date = release
+            .published_at
+            .as_deref()
+            .and_then(|date| date.get(..10))
+            .unwrap_or_default();
+        cprintln!("<s>{module_name} {}</> <dim>{date}</>", release.version);
+
+        match release.notes {
+            Some(notes) => {
+                for line in notes.lines().map(str::trim_end) {
+                    match line.strip_prefix('#') {
+                        Some(heading) => {
+                            cprintln!("  <s>{}</>", heading.trim_start_matches('#').trim())
+                        },
+                        None => println!("  {line}"),
+                    }
+                }
+            },
+            None => cprintln!("  <dim>(no release notes)</>"),
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -1,27 +1,675 @@
 // This is free and unencumbered software released into the public domain.
 
+use crate::{
+    profiles::{Profiles, create_private_dir, write_private},
+    secrets::SecretError,
+    selectors::Selection,
+    variables::{VariableError, VariableSource, Variables},
+};
 use asimov_env::paths::asimov_root;
-use asimov_module::{ModuleManifest, ReadVarError};
+use asimov_module::{ConfigurationVariable, ModuleManifest};
 use clientele::{
     StandardOptions,
     SysexitsError::{self, *},
 };
-use color_print::ceprintln;
+use color_print::{ceprintln, cprintln};
 use std::{
+    collections::{BTreeMap, BTreeSet},
     io::{BufRead, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 #[tokio::main]
 pub async fn config(
     module_name: String,
     unset: bool,
+    secret: bool,
     args: &[String],
+    profile: Option<String>,
+    _flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+    let profile = super::profile::resolve_profile(profile.as_deref()).await?;
+    let module_names = Selection::names(vec![module_name])
+        .resolve_installed(&registry)
+        .await?;
+
+    for module_name in module_names {
+        configure_module(&registry, &module_name, unset, secret, args, &profile).await?;
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn config_list(
+    module_name: Option<String>,
+    output: &str,
+    profile: Option<String>,
+    _flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+    let profile = super::profile::resolve_profile(profile.as_deref()).await?;
+    let module_names = select_modules(&registry, module_name.into_iter().collect()).await?;
+
+    let variables = Variables::default();
+    let mut rows = Vec::new();
+    for module_name in &module_names {
+        let manifest = read_manifest(&registry, module_name).await?;
+        let secrets = variables
+            .secrets()
+            .get(module_name)
+            .await
+            .inspect_err(|e| tracing::error!("failed to read secret variables: {e}"))?;
+
+        for var in manifest
+            .config
+            .iter()
+            .flat_map(|conf| conf.variables.iter())
+        {
+            let secret = secrets.contains(&var.name);
+            let mut error = None;
+            let (value, source) = match variables.read(&profile, module_name, var).await {
+                Ok(read) => read,
+                // Without the key, secrets are listed without their values:
+                Err(VariableError::Secret(e)) if secret => {
+                    tracing::debug!(?e, "unable to decrypt `{}`", var.name);
+                    error = Some(format!("unable to decrypt: {e}"));
+                    (None, VariableSource::File)
+                },
+                Err(e) => return Err(variable_error(&var.name, e)),
+            };
+            let value = match value {
+                Some(value) if secret => Some(crate::secrets::mask(&value)),
+                value => value,
+            };
+            rows.push(VariableRow {
+                module: module_name.clone(),
+                variable: var.name.clone(),
+                environment: var.environment.clone(),
+                value,
+                source,
+                secret,
+                error,
+            });
+        }
+    }
+
+    match output {
+        "json" | "jsonl" => {
+            let objects: Vec<_> = rows
+                .iter()
+                .map(|row| {
+                    serde_json::json!({
+                        "module": row.module,
+                        "profile": profile,
+                        "variable": row.variable,
+                        "value": row.value,
+                        "source": row.source.to_string(),
+                        "environment": row.environment,
+                        "secret": row.secret,
+                        "error": row.error,
+                    })
+                })
+                .collect();
+            if output == "json" {
+                println!("{}", serde_json::Value::Array(objects));
+            } else {
+                for object in objects {
+                    println!("{object}");
+                }
+            }
+        },
+        _ => print_variable_table(&rows),
+    }
+    Ok(())
+}
+
+/// A configuration variable as listed by `config --list`.
+struct VariableRow {
+    module: String,
+    variable: String,
+    environment: Option<String>,
+    /// The effective value, masked if the variable is secret.
+    value: Option<String>,
+    source: VariableSource,
+    secret: bool,
+    /// Why the value couldn't be read, e.g., a secret without its key.
+    error: Option<String>,
+}
+
+fn print_variable_table(rows: &[VariableRow]) {
+    if rows.is_empty() {
+        cprintln!("<dim>No configuration variables.</>");
+        return;
+    }
+
+    let sources: Vec<String> = rows
+        .iter()
+        .map(|row| match (&row.source, &row.environment) {
+            (VariableSource::Environment, Some(env_name)) => format!("env ({env_name})"),
+            (source, _) => source.to_string(),
+        })
+        .collect();
+    let values: Vec<&str> = rows
+        .iter()
+        .map(|row| match (&row.value, &row.error) {
+            (Some(value), _) => value.as_str(),
+            (None, Some(_)) => "(undecryptable)",
+            (None, None) => "-",
+        })
+        .collect();
+
+    let module_width = rows.iter().map(|row| row.module.len()).max().unwrap_or(0);
+    let variable_width = rows.iter().map(|row| row.variable.len()).max().unwrap_or(0);
+    let value_width = values.iter().map(|value| value.len()).max().unwrap_or(0);
+
+    cprintln!(
+        "<dim>{:<module_width$}  {:<variable_width$}  {:<value_width$}  SOURCE</>",
+        "MODULE",
+        "VARIABLE",
+        "VALUE",
+        module_width = module_width.max(6),
+        variable_width = variable_width.max(8),
+        value_width = value_width.max(5),
+    );
+    for ((row, value), source) in rows.iter().zip(values).zip(sources) {
+        let value = format!("{value:<width$}", width = value_width.max(5));
+        let value = match row.source {
+            _ if row.error.is_some() => color_print::cformat!("<r>{value}</>"),
+            VariableSource::Unset => color_print::cformat!("<y>{value}</>"),
+            _ => value,
+        };
+        cprintln!(
+            "<s>{:<module_width$}</>  {:<variable_width$}  {value}  <dim>{source}</>",
+            row.module,
+            row.variable,
+            module_width = module_width.max(6),
+            variable_width = variable_width.max(8),
+        );
+    }
+}
+
+/// Resolves the module names and patterns against the installed modules,
+/// selecting all installed modules if none are given.
+async fn select_modules(
+    registry: &asimov_registry::Registry,
+    names: Vec<String>,
+) -> Result<Vec<String>, SysexitsError> {
+    if !names.is_empty() {
+        return Selection {
+            names,
+            quiet: true,
+            ..Default::default()
+        }
+        .resolve_installed(registry)
+        .await;
+    }
+
+    let mut module_names: Vec<_> = registry
+        .installed_modules()
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to read installed modules: {e}");
+            EX_UNAVAILABLE
+        })?
+        .into_iter()
+        .map(|manifest| manifest.manifest.name)
+        .collect();
+    module_names.sort();
+    Ok(module_names)
+}
+
+/// The configured values of modules, by module and variable name.
+type ExportedConfig = BTreeMap<String, BTreeMap<String, String>>;
+
+/// The prefix of the comments in exported `.env` files which name the
+/// secret variables, as the format has no other way to mark them.
+const ENV_SECRET_COMMENT: &str = "# secret: ";
+
+#[tokio::main]
+pub async fn config_export(
+    module_names: Vec<String>,
+    format: Option<String>,
+    output: Option<PathBuf>,
+    include_secrets: bool,
+    profile: Option<String>,
     _flags: &StandardOptions,
 ) -> Result<(), SysexitsError> {
+    let format = match format {
+        Some(format) => format,
+        None => output
+            .as_deref()
+            .and_then(format_of)
+            .unwrap_or("yaml")
+            .into(),
+    };
+    if !matches!(format.as_str(), "yaml" | "json" | "env") {
+        ceprintln!("<s,r>error:</> unknown export format: `{format}`");
+        return Err(EX_USAGE);
+    }
+
     let registry = asimov_registry::Registry::default();
+    let profile = super::profile::resolve_profile(profile.as_deref()).await?;
+    let module_names = select_modules(&registry, module_names).await?;
+
+    let variables = Variables::default();
+    let mut config = ExportedConfig::new();
+    let mut env_names = BTreeMap::new();
+    let mut exported_secrets = BTreeSet::new();
+    let mut excluded = Vec::new();
+    for module_name in &module_names {
+        let manifest = read_manifest(&registry, module_name).await?;
+        let secrets = variables
+            .secrets()
+            .get(module_name)
+            .await
+            .inspect_err(|e| tracing::error!("failed to read secret variables: {e}"))?;
+
+        for var in manifest
+            .config
+            .iter()
+            .flat_map(|conf| conf.variables.iter())
+        {
+            if secrets.contains(&var.name) && !include_secrets {
+                if variables
+                    .is_stored(&profile, module_name, &var.name)
+                    .await
+                    .unwrap_or(false)
+                {
+                    excluded.push(format!("{module_name}.{}", var.name));
+                }
+                continue;
+            }
+            let Some(value) = variables
+                .read_stored(&profile, module_name, &var.name)
+                .await
+                .map_err(|e| variable_error(&var.name, e))?
+            else {
+                continue;
+            };
+            env_names.insert(
+                (module_name.clone(), var.name.clone()),
+                env_name(module_name, var),
+            );
+            if secrets.contains(&var.name) {
+                exported_secrets.insert((module_name.clone(), var.name.clone()));
+            }
+            config
+                .entry(module_name.clone())
+                .or_default()
+                .insert(var.name.clone(), value);
+        }
+    }
+
+    // Secret values are marked, to be stored encrypted again on import:
+    let file_config: BTreeMap<&String, BTreeMap<&String, FileValue>> = config
+        .iter()
+        .map(|(module_name, values)| {
+            let values = values
+                .iter()
+                .map(|(var_name, value)| {
+                    let value = ScalarValue::String(value.clone());
+                    let value =
+                        if exported_secrets.contains(&(module_name.clone(), var_name.clone())) {
+                            FileValue::Marked {
+                                value,
+                                secret: true,
+                            }
+                        } else {
+                            FileValue::Plain(value)
+                        };
+                    (var_name, value)
+                })
+                .collect();
+            (module_name, values)
+        })
+        .collect();
+
+    let content = match format.as_str() {
+        "json" => serde_json::to_string_pretty(&file_config).map(|json| json + "\n")?,
+        "env" => {
+            // Variables sharing an environment variable can't be told apart:
+            let mut variables_by_key: BTreeMap<&String, Vec<String>> = BTreeMap::new();
+            for ((module_name, var_name), key) in &env_names {
+                variables_by_key
+                    .entry(key)
+                    .or_default()
+                    .push(format!("{module_name}.{var_name}"));
+            }
+            let mut shared = false;
+            for (key, vars) in variables_by_key.iter().filter(|(_, vars)| vars.len() > 1) {
+                ceprintln!(
+                    "<s,r>error:</> The variables <s>{}</> share the environment variable <s>{key}</>.",
+                    vars.join(", ")
+                );
+                shared = true;
+            }
+            if shared {
+                ceprintln!("<s,dim>hint:</> Export them separately, or with: <s>--format yaml</>");
+                return Err(EX_DATAERR);
+            }
+
+            let header: String = exported_secrets
+                .iter()
+                .map(|var| format!("{ENV_SECRET_COMMENT}{}\n", env_names[var]))
+                .collect();
+            let lines: String = config
+                .iter()
+                .flat_map(|(module_name, values)| {
+                    values.iter().map(|(var_name, value)| {
+                        let key = &env_names[&(module_name.clone(), var_name.clone())];
+                        format!("{key}=\"{}\"\n", escape_env_value(value))
+                    })
+                })
+                .collect();
+            header + &lines
+        },
+        _ => serde_yml::to_string(&file_config).map_err(|e| {
+            tracing::error!("failed to serialize configuration: {e}");
+            EX_SOFTWARE
+        })?,
+    };
+
+    match output {
+        Some(path) => write_private(&path, content).await.map_err(|e| {
+            tracing::error!("failed to write `{}`: {e}", path.display());
+            EX_CANTCREAT
+        })?,
+        None => print!("{content}"),
+    }
+
+    if !excluded.is_empty() {
+        ceprintln!(
+            "<s,y>-</> Excluded the secret variables: <s>{}</>.",
+            excluded.join(", ")
+        );
+        ceprintln!("<s,dim>hint:</> To include them: <s>asimov module config export --secrets</>");
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn config_import(
+    file: PathBuf,
+    format: Option<String>,
+    profile: Option<String>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let Some(format) = format.or_else(|| format_of(&file).map(String::from)) else {
+        ceprintln!(
+            "<s,r>error:</> unable to tell the format of `{}` from its name.",
+            file.display()
+        );
+        ceprintln!("<s,dim>hint:</> Set the format with: <s>--format yaml|json|env</>");
+        return Err(EX_USAGE);
+    };
+
+    let registry = asimov_registry::Registry::default();
+    let profile = super::profile::resolve_profile(profile.as_deref()).await?;
+
+    let mut manifests = BTreeMap::new();
+    for manifest in registry.installed_modules().await.map_err(|e| {
+        tracing::error!("failed to read installed modules: {e}");
+        EX_UNAVAILABLE
+    })? {
+        manifests.insert(manifest.manifest.name.clone(), manifest.manifest);
+    }
+
+    let read_error = |e: &dyn std::fmt::Display| {
+        tracing::error!("failed to read `{}`: {e}", file.display());
+        EX_DATAERR
+    };
+    if !matches!(format.as_str(), "yaml" | "json" | "env") {
+        ceprintln!("<s,r>error:</> unknown import format: `{format}`");
+        return Err(EX_USAGE);
+    }
+    let content = tokio::fs::read_to_string(&file).await.map_err(|e| {
+        tracing::error!("failed to read `{}`: {e}", file.display());
+        EX_NOINPUT
+    })?;
+
+    let mut invalid = Vec::new();
+    let mut config = ExportedConfig::new();
+    let mut imported_secrets: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    match format.as_str() {
+        "yaml" | "json" => {
+            let values: BTreeMap<String, BTreeMap<String, FileValue>> = if format == "json" {
+                serde_json::from_str(&content).map_err(|e| read_error(&e))?
+            } else {
+                serde_yml::from_str(&content).map_err(|e| read_error(&e))?
+            };
+
+            for (module_name, values) in values {
+                let Some(manifest) = manifests.get(&module_name) else {
+                    invalid.push(format!("module `{module_name}` isn't installed"));
+                    continue;
+                };
+                for (var_name, value) in values {
+                    let Some(var) = manifest
+                        .config
+                        .iter()
+                        .flat_map(|conf| conf.variables.iter())
+                        .find(|var| var.name == var_name)
+                    else {
+                        invalid.push(format!(
+                            "`{var_name}` is not the name of a configuration variable for module `{module_name}`"
+                        ));
+                        continue;
+                    };
+                    let value = match value {
+                        FileValue::Plain(value) => value,
+                        FileValue::Marked { value, secret } => {
+                            if secret {
+                                if var.environment.is_none() {
+                                    invalid.push(format!(
+                                        "`{var_name}` of module `{module_name}` can't be stored encrypted, as the module has no environment variable to receive its value through"
+                                    ));
+                                    continue;
+                                }
+                                imported_secrets
+                                    .entry(module_name.clone())
+                                    .or_default()
+                                    .push(var_name.clone());
+                            }
+                            value
+                        },
+                    };
+                    config
+                        .entry(module_name.clone())
+                        .or_default()
+                        .insert(var_name, value.to_string());
+                }
+            }
+        },
+        _ => {
+            let secret_keys: Vec<&str> = content
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix(ENV_SECRET_COMMENT))
+                .map(str::trim)
+                .collect();
+            for entry in clientele::crates::dotenvy::from_read_iter(content.as_bytes()) {
+                let (key, value) = entry.map_err(|e| read_error(&e))?;
+                let matching: Vec<(&String, &ConfigurationVariable)> = manifests
+                    .iter()
+                    .flat_map(|(module_name, manifest)| {
+                        manifest
+                            .config
+                            .iter()
+                            .flat_map(|conf| conf.variables.iter())
+                            .map(move |var| (module_name, var))
+                    })
+                    .filter(|(module_name, var)| env_name(module_name, var) == key)
+                    .collect();
+                let [(module_name, var)] = matching.as_slice() else {
+                    invalid.push(if matching.is_empty() {
+                        format!("`{key}` is not a configuration variable of any installed module")
+                    } else {
+                        format!(
+                            "`{key}` is the environment variable of several variables: {}",
+                            matching
+                                .iter()
+                                .map(|(module_name, var)| format!("{module_name}.{}", var.name))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )
+                    });
+                    continue;
+                };
+                if secret_keys.contains(&key.as_str()) {
+                    imported_secrets
+                        .entry(module_name.to_string())
+                        .or_default()
+                        .push(var.name.clone());
+                }
+                config
+                    .entry(module_name.to_string())
+                    .or_default()
+                    .insert(var.name.clone(), value);
+            }
+        },
+    }
+
+    if !invalid.is_empty() {
+        for message in &invalid {
+            ceprintln!("<s,r>error:</> {message}");
+        }
+        ceprintln!(
+            "<s,dim>hint:</> Nothing was imported. Check the file against the installed modules with: <s>asimov module config --list</>"
+        );
+        return Err(EX_DATAERR);
+    }
+
+    let variables = Variables::default();
+    for (module_name, values) in &config {
+        if let Some(secrets) = imported_secrets.get(module_name) {
+            let created_key = variables
+                .mark_secret(module_name, secrets)
+                .await
+                .map_err(|e| variable_error(&secrets.join(", "), e))?;
+            print_created_key(created_key);
+        }
+        for (var_name, value) in values {
+            let created_key = variables
+                .write(&profile, module_name, var_name, value)
+                .await
+                .map_err(|e| variable_error(var_name, e))?;
+            print_created_key(created_key);
+        }
+        if flags.verbose > 0 {
+            cprintln!(
+                "<s,g>✓</> Imported {} variable(s) for module <s>{module_name}</>.",
+                values.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A value in an exported YAML or JSON file, where the values of secret
+/// variables are marked, e.g., `{value: sk-..., secret: true}`.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum FileValue {
+    Plain(ScalarValue),
+    Marked {
+        value: ScalarValue,
+        #[serde(default)]
+        secret: bool,
+    },
+}
+
+/// A scalar value in an exported YAML or JSON file.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum ScalarValue {
+    String(String),
+    Bool(bool),
+    Number(serde_json::Number),
+}
+
+impl core::fmt::Display for ScalarValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::String(value) => f.write_str(value),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Number(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Returns the export format matching the file's name, if any.
+fn format_of(path: &Path) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_str()?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml" | "yml") => Some("yaml"),
+        Some("json") => Some("json"),
+        Some("env") => Some("env"),
+        _ if file_name == ".env" || file_name.starts_with(".env.") => Some("env"),
+        _ => None,
+    }
+}
+
+/// Returns the name of the variable in `.env` files: its environment
+/// variable if it has one, e.g., `OPENAI_API_KEY`, so that the file also
+/// works with the module's programs, otherwise `ASIMOV_<MODULE>_<VARIABLE>`.
+fn env_name(module_name: &str, var: &ConfigurationVariable) -> String {
+    match &var.environment {
+        Some(env_name) => env_name.clone(),
+        None => format!("ASIMOV_{module_name}_{}", var.name)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Escapes the value for a double-quoted `.env` value, which would otherwise
+/// be subject to variable substitution.
+fn escape_env_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+async fn read_manifest(
+    registry: &asimov_registry::Registry,
+    module_name: &str,
+) -> Result<ModuleManifest, SysexitsError> {
+    registry
+        .read_manifest(module_name)
+        .await
+        .map(|manifest| manifest.manifest)
+        .map_err(|e| {
+            tracing::error!("failed to read manifest for module `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })
+}
+
+async fn configure_module(
+    registry: &asimov_registry::Registry,
+    module_name: &str,
+    unset: bool,
+    secret: bool,
+    args: &[String],
+    profile: &str,
+) -> Result<(), SysexitsError> {
+    let variables = Variables::default();
     let manifest = registry
-        .read_manifest(&module_name)
+        .read_manifest(module_name)
         .await
         .map_err(|e| {
             tracing::error!("failed to read manifest for module `{module_name}`: {e}");
@@ -41,42 +689,55 @@ pub async fn config(
         .unwrap_or_default();
 
     if !conf_vars.is_empty() {
-        let profile = "default"; // TODO
+        create_config_dir(profile, module_name).await?;
 
-        let conf_dir = asimov_root()
-            .join("configs")
-            .join(profile)
-            .join(&module_name);
+        if secret {
+            let names: Vec<String> = match args.len() {
+                1 => args.to_vec(),
+                _ => args.iter().step_by(2).cloned().collect(),
+            };
+            for name in &names {
+                let Some(var) = conf_vars.iter().find(|var| var.name == *name) else {
+                    ceprintln!(
+                        "<s,r>error:</> `{name}` is not the name of a configuration variable for <s>{module_name}</> module"
+                    );
+                    return Err(EX_USAGE);
+                };
+                // Modules read configuration files as plain text, so they
+                // can only receive secrets through the environment:
+                if var.environment.is_none() {
+                    ceprintln!(
+                        "<s,r>error:</> Variable `{name}` of module <s>{module_name}</> can't be stored encrypted, as the module has no environment variable to receive its value through."
+                    );
+                    return Err(EX_USAGE);
+                }
+            }
+            // encrypt the current values in every profile, rather than show
+            // them or leave plain-text copies behind
+            let created_key = variables
+                .mark_secret(module_name, &names)
+                .await
+                .map_err(|e| variable_error(&names.join(", "), e))?;
+            print_created_key(created_key);
 
-        tokio::fs::create_dir_all(&conf_dir)
-            .await
-            .inspect_err(|e| {
-                tracing::error!(
-                    "failed to create configuration directory for module `{module_name}`: {e}"
-                )
-            })?;
+            if args.len() == 1 {
+                print_secret_hint(module_name, conf_vars, &names);
+                return run_configurator(module_name, &manifest, profile).await;
+            }
+        }
 
         if unset {
             let vars: Vec<String> = if !args.is_empty() {
                 args.to_vec()
-            } else if let Some(conf) = manifest.config {
-                // unset all vars
-                conf.variables.into_iter().map(|v| v.name).collect()
             } else {
-                return Ok(());
+                // unset all vars
+                conf_vars.iter().map(|v| v.name.clone()).collect()
             };
 
             for var in &vars {
-                let var_file = conf_dir.join(var);
-                tokio::fs::remove_file(&var_file)
+                variables
+                    .remove(profile, module_name, var)
                     .await
-                    .or_else(|e| {
-                        if e.kind() == tokio::io::ErrorKind::NotFound {
-                            Ok(())
-                        } else {
-                            Err(e)
-                        }
-                    })
                     .inspect_err(|e| {
                         tracing::error!("failed to unset configuration variable `{var}`: {e}")
                     })?;
@@ -86,88 +747,31 @@ pub async fn config(
         }
 
         if args.is_empty() {
-            // interactively prompt for each value in the config
-
-            let mut stdout = std::io::stdout().lock();
-            let mut stdin = std::io::stdin().lock().lines();
-
-            for var in conf_vars {
-                let var_file = conf_dir.join(&var.name);
-
-                let md = tokio::fs::metadata(&var_file).await;
-
-                let current_value = tokio::fs::read_to_string(&var_file).await.ok();
-
-                let info_text = if current_value.is_some() {
-                    "(press Enter to keep current)"
-                } else if let Some(default_value) = &var.default_value {
-                    &format!("(optional, default: `{default_value}`)")
-                } else {
-                    "(required)"
-                };
-
-                writeln!(&mut stdout, "Enter value for `{}` {info_text}", var.name)?;
-
-                if let Some(current) = &current_value {
-                    writeln!(&mut stdout, "Current value: `{}`", current.trim())?;
-                }
-
-                if let Some(desc) = &var.description {
-                    writeln!(&mut stdout, "Description: {desc}");
-                }
-
-                write!(&mut stdout, "> ")?;
-                stdout.flush()?;
-                let value = stdin.next().ok_or(EX_NOINPUT)??;
-                let value = value.trim();
-                if value.is_empty() {
-                    continue;
-                }
-
-                tokio::fs::write(&var_file, &value).await?;
-            }
-
-            writeln!(&mut stdout, "Configuration:")?;
-            for var in conf_vars {
-                manifest
-                    .variable(&var.name, Some(profile))
-                    .inspect(|val| {
-                        writeln!(&mut stdout, "\t{}: {}", var.name, val);
-                    })
-                    .inspect_err(|e| match e {
-                        asimov_module::ReadVarError::UnconfiguredVar(_) => {
-                            ceprintln!("\t{}: <s,y>warn:</> {e}", var.name);
-                        },
-                        _ => {
-                            ceprintln!("\t{}: <s,r>error:</> {e}", var.name);
-                        },
-                    });
-            }
+            prompt_variables(module_name, &manifest, profile).await?;
         } else if args.len() == 1 {
             // one arg, fetch the value
 
             let name = &args[0];
             if manifest
                 .config
+                .as_ref()
                 .is_some_and(|conf| conf.variables.iter().any(|var| var.name == *name))
             {
-                let var_file = conf_dir.join(name);
-                if let Ok(current) = tokio::fs::read_to_string(&var_file).await {
-                    println!("{}", current.trim());
+                let current = variables
+                    .read_stored(profile, module_name, name)
+                    .await
+                    .map_err(|e| variable_error(name, e))?;
+                if let Some(current) = current {
+                    println!("{current}");
                 }
             } else {
                 ceprintln!("<s,r>error:</> unrecognized configuration variable key: `{name}`");
                 return Err(EX_USAGE);
             }
-        } else if args.len() % 2 == 0 {
+        } else if args.len().is_multiple_of(2) {
             // pair(s) of (key,value), write into config file(s)
 
-            let mut chunks = args.chunks_exact(2);
-            loop {
-                let Some([name, value]) = chunks.next() else {
-                    break;
-                };
-
+            for [name, value] in args.as_chunks::<2>().0 {
                 // must be a known configuration variable, otherwise stop
                 if !conf_vars.iter().any(|var| var.name == *name) {
                     ceprintln!(
@@ -176,10 +780,25 @@ pub async fn config(
                     return Err(EX_USAGE);
                 }
 
-                let var_file = conf_dir.join(name);
-
-                tokio::fs::write(&var_file, &value).await?;
+                let created_key = variables
+                    .write(profile, module_name, name, value)
+                    .await
+                    .map_err(|e| variable_error(name, e))?;
+                print_created_key(created_key);
             }
+
+            let secrets = variables
+                .secrets()
+                .get(module_name)
+                .await
+                .inspect_err(|e| tracing::error!("failed to read secret variables: {e}"))?;
+            let names: Vec<String> = args
+                .iter()
+                .step_by(2)
+                .filter(|name| secrets.contains(name))
+                .cloned()
+                .collect();
+            print_secret_hint(module_name, conf_vars, &names);
         } else {
             ceprintln!(
                 "<s,r>error:</> invalid number of arguments: expected 0, 1, or key-value pairs (even count), got {}",
@@ -190,6 +809,129 @@ pub async fn config(
         }
     }
 
+    run_configurator(module_name, &manifest, profile).await
+}
+
+/// Creates (if needed) and returns the directory holding the configured
+/// variables of the module for the given profile.
+pub(crate) async fn create_config_dir(
+    profile: &str,
+    module_name: &str,
+) -> Result<PathBuf, SysexitsError> {
+    let conf_dir = Profiles::default().dir(profile).join(module_name);
+
+    create_private_dir(&conf_dir).await.inspect_err(|e| {
+        tracing::error!("failed to create configuration directory for module `{module_name}`: {e}")
+    })?;
+
+    Ok(conf_dir)
+}
+
+/// Interactively prompts for each configuration variable of the module, then
+/// prints the resulting configuration.
+pub(crate) async fn prompt_variables(
+    module_name: &str,
+    manifest: &ModuleManifest,
+    profile: &str,
+) -> Result<(), SysexitsError> {
+    let conf_vars = manifest
+        .config
+        .as_ref()
+        .map(|c| c.variables.as_slice())
+        .unwrap_or_default();
+
+    create_config_dir(profile, module_name).await?;
+
+    let variables = Variables::default();
+    let secrets = variables
+        .secrets()
+        .get(module_name)
+        .await
+        .inspect_err(|e| tracing::error!("failed to read secret variables: {e}"))?;
+    let display = |name: &str, value: &str| {
+        if secrets.iter().any(|secret| secret == name) {
+            crate::secrets::mask(value)
+        } else {
+            value.to_string()
+        }
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    let mut stdin = std::io::stdin().lock().lines();
+
+    for var in conf_vars {
+        let current_value = variables
+            .read_stored(profile, module_name, &var.name)
+            .await
+            .map_err(|e| variable_error(&var.name, e))?;
+
+        let info_text = if current_value.is_some() {
+            "(press Enter to keep current)"
+        } else if let Some(default_value) = &var.default_value {
+            &format!("(optional, default: `{default_value}`)")
+        } else {
+            "(required)"
+        };
+
+        writeln!(&mut stdout, "Enter value for `{}` {info_text}", var.name)?;
+
+        if let Some(current) = &current_value {
+            writeln!(
+                &mut stdout,
+                "Current value: `{}`",
+                display(&var.name, current)
+            )?;
+        }
+
+        if let Some(desc) = &var.description {
+            writeln!(&mut stdout, "Description: {desc}")?;
+        }
+
+        write!(&mut stdout, "> ")?;
+        stdout.flush()?;
+        let value = stdin.next().ok_or(EX_NOINPUT)??;
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        let created_key = variables
+            .write(profile, module_name, &var.name, value)
+            .await
+            .map_err(|e| variable_error(&var.name, e))?;
+        print_created_key(created_key);
+    }
+
+    writeln!(&mut stdout, "Configuration:")?;
+    for var in conf_vars {
+        match variables.read(profile, module_name, var).await {
+            Ok((Some(val), _)) => {
+                writeln!(&mut stdout, "\t{}: {}", var.name, display(&var.name, &val))?
+            },
+            Ok((None, _)) => {
+                ceprintln!(
+                    "\t{}: <s,y>warn:</> configuration variable `{}` is not configured",
+                    var.name,
+                    var.name
+                );
+            },
+            Err(e) => {
+                ceprintln!("\t{}: <s,r>error:</> {e}", var.name);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the module's configurator program, if it provides one and it is
+/// installed, passing it the profile in `ASIMOV_PROFILE` and the decrypted
+/// values of secret variables in their environment variables.
+pub(crate) async fn run_configurator(
+    module_name: &str,
+    manifest: &ModuleManifest,
+    profile: &str,
+) -> Result<(), SysexitsError> {
     let configurator_name = format!("asimov-{module_name}-configurator");
 
     let provides_configurator = manifest.provides.programs.contains(&configurator_name);
@@ -199,7 +941,10 @@ pub async fn config(
     let configurator_exists = tokio::fs::try_exists(&conf_bin).await.unwrap_or(false);
 
     if provides_configurator && configurator_exists {
+        let secret_env = secret_environment(module_name, manifest, profile).await?;
         std::process::Command::new(&conf_bin)
+            .env("ASIMOV_PROFILE", profile)
+            .envs(secret_env)
             .stdin(std::process::Stdio::inherit())
             .stdout(std::process::Stdio::inherit())
             .stderr(std::process::Stdio::inherit())
@@ -209,3 +954,119 @@ pub async fn config(
 
     Ok(())
 }
+
+/// Returns the environment variables through which the module receives the
+/// decrypted values of its secret variables, as it can't read them from the
+/// encrypted files itself.
+pub(crate) async fn secret_environment(
+    module_name: &str,
+    manifest: &ModuleManifest,
+    profile: &str,
+) -> Result<Vec<(String, String)>, SysexitsError> {
+    let variables = Variables::default();
+    let secrets = variables
+        .secrets()
+        .get(module_name)
+        .await
+        .inspect_err(|e| tracing::error!("failed to read secret variables: {e}"))?;
+
+    let mut env = Vec::new();
+    for var in manifest
+        .config
+        .iter()
+        .flat_map(|conf| conf.variables.iter())
+    {
+        let Some(env_name) = &var.environment else {
+            continue;
+        };
+        if !secrets.contains(&var.name) || std::env::var_os(env_name).is_some() {
+            continue;
+        }
+        if let Some(value) = variables
+            .read_stored(profile, module_name, &var.name)
+            .await
+            .map_err(|e| variable_error(&var.name, e))?
+        {
+            env.push((env_name.clone(), value));
+        }
+    }
+    Ok(env)
+}
+
+/// Logs the failure to read or write a configuration variable.
+pub(crate) fn variable_error(var_name: &str, error: VariableError) -> SysexitsError {
+    tracing::error!("failed to access configuration variable `{var_name}`: {error}");
+    match error {
+        VariableError::Secret(SecretError::NoKey) => {
+            ceprintln!(
+                "<s,dim>hint:</> Set the passphrase with: <s>export ASIMOV_PASSPHRASE=...</>"
+            );
+            EX_CONFIG
+        },
+        VariableError::Secret(SecretError::WrongKey) => EX_CONFIG,
+        _ => EX_IOERR,
+    }
+}
+
+/// Warns that the module can't read the values of the secret variables
+/// itself, and explains how it receives them instead.
+fn print_secret_hint(module_name: &str, conf_vars: &[ConfigurationVariable], names: &[String]) {
+    let env_names: Vec<&str> = conf_vars
+        .iter()
+        .filter(|var| names.contains(&var.name))
+        .filter_map(|var| var.environment.as_deref())
+        .collect();
+    if env_names.is_empty() {
+        return;
+    }
+    ceprintln!(
+        "<s,y>warn:</> Module <s>{module_name}</> can't read encrypted values itself, it only receives them through <s>{}</>.",
+        env_names.join(", ")
+    );
+    ceprintln!(
+        "<s,dim>hint:</> Run the module with its secrets passed: <s>asimov module exec {module_name} -- <<command>></>"
+    );
+}
+
+/// Notifies that a key file was created for encrypting secrets.
+pub(crate) fn print_created_key(created_key: Option<PathBuf>) {
+    if let Some(path) = created_key {
+        cprintln!(
+            "<s,y>-</> Created the key file <s>{}</> for encrypting secrets. Keep it safe, secrets can't be decrypted without it.",
+            path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_values() {
+        let var = ConfigurationVariable {
+            name: "api-key".into(),
+            ..Default::default()
+        };
+        assert_eq!(env_name("open-ai", &var), "ASIMOV_OPEN_AI_API_KEY");
+
+        let value = "a $HOME \"b\" \\c\nd";
+        let line = format!("KEY=\"{}\"\n", escape_env_value(value));
+        let parsed: Vec<_> = clientele::crates::dotenvy::from_read_iter(line.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(parsed, [("KEY".to_string(), value.to_string())]);
+
+        let values: BTreeMap<String, FileValue> =
+            serde_yml::from_str("api_key: {value: sk-123, secret: true}\nport: 8080\n").unwrap();
+        assert!(matches!(
+            &values["api_key"],
+            FileValue::Marked { value, secret: true } if value.to_string() == "sk-123"
+        ));
+        assert!(matches!(&values["port"], FileValue::Plain(value) if value.to_string() == "8080"));
+
+        assert_eq!(format_of(Path::new("config.yml")), Some("yaml"));
+        assert_eq!(format_of(Path::new(".env.production")), Some("env"));
+        assert_eq!(format_of(Path::new("config.txt")), None);
+    }
+}
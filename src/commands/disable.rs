@@ -3,16 +3,55 @@
 use crate::{
     StandardOptions,
     SysexitsError::{self, *},
+    dependencies::Dependencies,
+    selectors::Selection,
 };
-use color_print::cprintln;
+use color_print::{ceprintln, cprintln};
 
 #[tokio::main]
 pub async fn disable(
-    module_names: Vec<String>,
+    selection: Selection,
+    cascade: bool,
     flags: &StandardOptions,
 ) -> Result<(), SysexitsError> {
     let registry = asimov_registry::Registry::default();
-    for module_name in module_names {
+    let mut module_names = selection.resolve_installed(&registry).await?;
+
+    let deps = Dependencies::installed(&registry).await.map_err(|e| {
+        tracing::error!("failed to read installed modules: {e}");
+        EX_UNAVAILABLE
+    })?;
+
+    let mut dependents = Vec::new();
+    for dependent in deps.all_dependents(&module_names) {
+        let is_enabled = registry.is_module_enabled(&dependent).await.map_err(|e| {
+            tracing::error!("failed to check if module is enabled: {e}");
+            EX_UNAVAILABLE
+        })?;
+        if is_enabled {
+            dependents.push(dependent);
+        }
+    }
+
+    if !dependents.is_empty() {
+        if cascade {
+            cprintln!(
+                "<s,y>-</> Also disabling the dependent modules: <s>{}</>.",
+                dependents.join(", ")
+            );
+            module_names.extend(dependents);
+        } else {
+            ceprintln!(
+                "<s,y>warn:</> Disabling modules which enabled modules require: <s>{}</>.",
+                dependents.join(", ")
+            );
+            ceprintln!(
+                "<s,dim>hint:</> Disable the dependent modules too with: <s>asimov module disable --cascade</>"
+            );
+        }
+    }
+
+    for module_name in deps.removal_order(&module_names) {
         if flags.verbose > 1 {
             cprintln!("<s,c>»</> Disabling module <s>{module_name}</>...");
         }
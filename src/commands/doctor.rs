@@ -0,0 +1,130 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    profiles::Profiles,
+    secrets::SecretKey,
+};
+use color_print::{ceprintln, cprintln};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+#[tokio::main]
+pub async fn doctor(flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let mut problems = 0;
+
+    if flags.verbose > 1 {
+        cprintln!("<s,c>»</> Checking the permissions of configuration files...");
+    }
+    let profiles = Profiles::default();
+    let mut exposed = exposed_paths(profiles.root()).await.map_err(|e| {
+        tracing::error!("failed to check configuration files: {e}");
+        EX_IOERR
+    })?;
+    let key_file = SecretKey::key_file_path();
+    if let Ok(Some(mode)) = exposed_mode(&key_file).await {
+        exposed.push((key_file, mode));
+    }
+    for (path, mode) in &exposed {
+        ceprintln!(
+            "<s,y>warn:</> Configuration path {} is accessible by other users (mode {mode:o}).",
+            path.display()
+        );
+    }
+    if !exposed.is_empty() {
+        ceprintln!(
+            "<s,dim>hint:</> To restrict access: <s>chmod -R go-rwx {}</>",
+            profiles.root().display()
+        );
+        problems += exposed.len();
+    }
+
+    if problems > 0 {
+        return Err(EX_CONFIG);
+    }
+    cprintln!("<s,g>✓</> No problems found.");
+    Ok(())
+}
+
+/// Returns the directory and the files and directories in it, recursively,
+/// which are accessible by the group or by others, along with their modes.
+async fn exposed_paths(dir: &Path) -> io::Result<Vec<(PathBuf, u32)>> {
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut exposed = Vec::new();
+    if let Some(mode) = exposed_mode(dir).await? {
+        exposed.push((dir.to_path_buf(), mode));
+    }
+    while let Some(entry) = read_dir.next_entry().await? {
+        let path = entry.path();
+        if entry.file_type().await?.is_dir() {
+            exposed.extend(Box::pin(exposed_paths(&path)).await?);
+        } else if let Some(mode) = exposed_mode(&path).await? {
+            exposed.push((path, mode));
+        }
+    }
+    exposed.sort();
+    Ok(exposed)
+}
+
+/// Returns the mode of the file or directory if it's accessible by the
+/// group or by others.
+#[cfg(unix)]
+async fn exposed_mode(path: &Path) -> io::Result<Option<u32>> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = tokio::fs::metadata(path).await?.permissions().mode() & 0o777;
+    Ok(Some(mode).filter(|mode| mode & 0o077 != 0))
+}
+
+#[cfg(not(unix))]
+async fn exposed_mode(_path: &Path) -> io::Result<Option<u32>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exposed_paths() {
+        use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+        let dir = temp_dir::TempDir::new().unwrap();
+        let configs = dir.child("configs");
+        let module_dir = configs.join("default").join("foo");
+        std::fs::create_dir_all(&module_dir).unwrap();
+        std::fs::write(module_dir.join("model"), "gpt").unwrap();
+        std::fs::write(module_dir.join("api_key.enc"), "...").unwrap();
+        for (path, mode) in [
+            (&configs, 0o700),
+            (&configs.join("default"), 0o700),
+            (&module_dir, 0o755),
+            (&module_dir.join("model"), 0o644),
+            (&module_dir.join("api_key.enc"), 0o600),
+        ] {
+            std::fs::set_permissions(path, Permissions::from_mode(mode)).unwrap();
+        }
+
+        assert_eq!(
+            exposed_paths(&configs).await.unwrap(),
+            [
+                (module_dir.clone(), 0o755),
+                (module_dir.join("model"), 0o644)
+            ]
+        );
+        assert!(
+            exposed_paths(&dir.child("missing"))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    installer::{Installer, PlatformInfo, platform::detect_platform},
+    selectors::Selection,
+};
+use color_print::{ceprintln, cprintln};
+use std::path::PathBuf;
+
+#[tokio::main]
+pub async fn download(
+    selection: Selection,
+    version: Option<String>,
+    target: Option<String>,
+    output_dir: Option<PathBuf>,
+    pre: bool,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let installer = Installer::default();
+    let module_names = selection.resolve_catalog(&installer).await?;
+
+    let platform = match target {
+        Some(target) => PlatformInfo::from_target(&target).ok_or_else(|| {
+            ceprintln!("<s,r>error:</> unsupported target: `{target}`");
+            EX_USAGE
+        })?,
+        None => detect_platform(),
+    };
+
+    let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("."));
+    tokio::fs::create_dir_all(&output_dir)
+        .await
+        .inspect_err(|e| tracing::error!("failed to create output directory: {e}"))?;
+
+    for module_name in module_names {
+        let target_version = if let Some(ref want) = version {
+            want.clone()
+        } else {
+            let channel = super::install::module_channel(&module_name, pre).await?;
+            installer
+                .fetch_latest_on_channel(&module_name, channel)
+                .await
+                .map_err(|e| {
+                    tracing::error!(
+                        "unable to find latest release for module `{module_name}`: {e}"
+                    );
+                    EX_UNAVAILABLE
+                })?
+        };
+
+        if flags.verbose > 1 {
+            cprintln!(
+                "<s,c>»</> Downloading module <s>{module_name}</> version <s>{target_version}</> for <s>{platform}</>..."
+            );
+        }
+
+        let (filename, cached_path) = installer
+            .download_matching_asset(&module_name, &target_version, &platform)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to download module `{module_name}`: {e}");
+                EX_UNAVAILABLE
+            })?;
+
+        let output_path = output_dir.join(&filename);
+        tokio::fs::copy(&cached_path, &output_path)
+            .await
+            .inspect_err(|e| tracing::error!("failed to write `{}`: {e}", output_path.display()))?;
+
+        println!("{}", output_path.display());
+    }
+
+    Ok(())
+}
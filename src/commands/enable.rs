@@ -1,30 +1,205 @@
 // This is free and unencumbered software released into the public domain.
 
+use super::install::{check_configuration, print_missing_variables, warn_unread_profile};
 use crate::{
     StandardOptions,
     SysexitsError::{self, *},
+    installer::{InstallOptions, Installer},
+    reasons::{InstallReason, InstallReasons},
+    selectors::Selection,
 };
-use color_print::cprintln;
+use asimov_module::InstalledModuleManifest;
+use asimov_registry::error::ManifestError;
+use color_print::{ceprintln, cprintln};
+use std::collections::BTreeSet;
 
 #[tokio::main]
 pub async fn enable(
-    module_names: Vec<String>,
+    selection: Selection,
+    force: bool,
+    no_prompt: bool,
+    profile: Option<String>,
     flags: &StandardOptions,
 ) -> Result<(), SysexitsError> {
     let registry = asimov_registry::Registry::default();
-    for module_name in module_names {
+    let installer = Installer::default();
+    let profile = super::profile::resolve_profile(profile.as_deref()).await?;
+    warn_unread_profile(&profile);
+    let module_names = selection.resolve_installed(&registry).await?;
+
+    // Enable the required modules first, installing missing ones:
+    let mut planner = Planner {
+        registry: &registry,
+        installer: &installer,
+        no_prompt,
+        flags,
+        visited: BTreeSet::new(),
+        order: Vec::new(),
+    };
+    for module_name in &module_names {
+        planner.visit(module_name, None).await?;
+    }
+
+    let mut refused = Vec::new();
+    for (manifest, required_by) in planner.order {
+        let module_name = &manifest.manifest.name;
+        let requires = manifest
+            .manifest
+            .requires
+            .as_ref()
+            .map(|requires| requires.modules.as_slice())
+            .unwrap_or_default();
+        if let Some(required) = requires.iter().find(|m| refused.contains(*m)) {
+            ceprintln!(
+                "<s,r>error:</> Module <s>{module_name}</> can't be enabled as its required module <s>{required}</> isn't enabled."
+            );
+            refused.push(module_name.clone());
+            continue;
+        }
+
+        if required_by.is_some()
+            && registry
+                .is_module_enabled(module_name)
+                .await
+                .unwrap_or(false)
+        {
+            continue;
+        }
+
+        let missing_variables =
+            check_configuration(module_name, &manifest.manifest, !no_prompt).await?;
+        if !missing_variables.is_empty() {
+            if force {
+                ceprintln!(
+                    "<s,y>warn:</> Enabling module <s>{module_name}</> despite missing configuration."
+                );
+            } else {
+                ceprintln!(
+                    "<s,r>error:</> Module <s>{module_name}</> can't be enabled due to missing configuration."
+                );
+                print_missing_variables(module_name, &missing_variables, &profile);
+                ceprintln!(
+                    "<s,dim>hint:</>   To enable regardless: <s>asimov module enable --force {module_name}</s>"
+                );
+                refused.push(module_name.clone());
+                continue;
+            }
+        }
+
         if flags.verbose > 1 {
             cprintln!("<s,c>»</> Enabling module <s>{module_name}</>...");
         }
 
-        registry.enable_module(&module_name).await.map_err(|e| {
+        registry.enable_module(module_name).await.map_err(|e| {
             tracing::error!("failed to enable module `{module_name}`: {e}");
             EX_UNAVAILABLE
         })?;
 
-        if flags.verbose > 0 {
-            cprintln!("<s,g>✓</> Enabled module <s>{module_name}</>.");
+        match required_by {
+            Some(dependent) => cprintln!(
+                "<s,g>✓</> Enabled module <s>{module_name}</>, which <s>{dependent}</> requires."
+            ),
+            None if flags.verbose > 0 => {
+                cprintln!("<s,g>✓</> Enabled module <s>{module_name}</>.")
+            },
+            None => (),
         }
     }
+
+    if !refused.is_empty() {
+        return Err(EX_CONFIG);
+    }
     Ok(())
 }
+
+/// Orders the modules to enable so that required modules come first.
+struct Planner<'a> {
+    registry: &'a asimov_registry::Registry,
+    installer: &'a Installer,
+    no_prompt: bool,
+    flags: &'a StandardOptions,
+    visited: BTreeSet<String>,
+    /// The manifests of the modules to enable, along with the module which
+    /// requires each, if it wasn't selected itself.
+    order: Vec<(InstalledModuleManifest, Option<String>)>,
+}
+
+impl Planner<'_> {
+    async fn visit(
+        &mut self,
+        module_name: &str,
+        required_by: Option<&str>,
+    ) -> Result<(), SysexitsError> {
+        if !self.visited.insert(module_name.into()) {
+            return Ok(());
+        }
+
+        let manifest = match self.registry.read_manifest(module_name).await {
+            Ok(manifest) => manifest,
+            Err(ManifestError::NotInstalled) if let Some(dependent) = required_by => {
+                self.install_required(module_name, dependent).await?
+            },
+            Err(e) => {
+                tracing::error!("failed to read module manifest for `{module_name}`: {e}");
+                return Err(EX_UNAVAILABLE);
+            },
+        };
+
+        let requires = manifest
+            .manifest
+            .requires
+            .as_ref()
+            .map(|requires| requires.modules.clone())
+            .unwrap_or_default();
+        for required in requires {
+            Box::pin(self.visit(&required, Some(module_name))).await?;
+        }
+
+        self.order.push((manifest, required_by.map(String::from)));
+        Ok(())
+    }
+
+    /// Installs a missing required module, if the user agrees.
+    async fn install_required(
+        &self,
+        module_name: &str,
+        dependent: &str,
+    ) -> Result<InstalledModuleManifest, SysexitsError> {
+        ceprintln!(
+            "<s,y>warn:</> Module <s>{dependent}</> requires module <s>{module_name}</>, which isn't installed."
+        );
+        if self.no_prompt
+            || !crate::is_interactive()
+            || !crate::confirm(&format!("Install module `{module_name}` now?"), true)?
+        {
+            ceprintln!("<s,dim>hint:</> To install it: <s>asimov module install {module_name}</>");
+            return Err(EX_UNAVAILABLE);
+        }
+
+        if self.flags.verbose > 1 {
+            cprintln!("<s,c>»</> Installing module <s>{module_name}</>...");
+        }
+
+        self.installer
+            .install_module(module_name, &InstallOptions::default())
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to install module `{module_name}`: {e}");
+                EX_UNAVAILABLE
+            })?;
+        InstallReasons::default()
+            .set(module_name, InstallReason::Auto)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to record install reason of `{module_name}`: {e}");
+                EX_IOERR
+            })?;
+
+        cprintln!("<s,g>✓</> Installed module <s>{module_name}</>.");
+
+        self.registry.read_manifest(module_name).await.map_err(|e| {
+            tracing::error!("failed to read module manifest for `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })
+    }
+}
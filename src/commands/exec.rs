@@ -0,0 +1,70 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    profiles::DEFAULT_PROFILE,
+};
+use color_print::{ceprintln, cprintln};
+
+/// Runs the command with the decrypted values of the modules' secret
+/// variables in their environment variables, which is the only way modules
+/// receive them, as they read the `default` profile as plain text.
+#[tokio::main]
+pub async fn exec(
+    module_names: Vec<String>,
+    command: Vec<String>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+    let Some((program, args)) = command.split_first() else {
+        ceprintln!("<s,r>error:</> no command to run.");
+        return Err(EX_USAGE);
+    };
+
+    let mut env = Vec::new();
+    for module_name in &module_names {
+        let manifest = registry.read_manifest(module_name).await.map_err(|e| {
+            tracing::error!("failed to read manifest for module `{module_name}`: {e}");
+            if let asimov_registry::error::ManifestError::NotInstalled = e {
+                ceprintln!(
+                    "<s,dim>hint:</> Check if the module is installed with: <s>asimov module list</>"
+                );
+            }
+            EX_UNAVAILABLE
+        })?;
+        env.extend(
+            super::config::secret_environment(module_name, &manifest.manifest, DEFAULT_PROFILE)
+                .await?,
+        );
+    }
+
+    if flags.verbose > 1 {
+        let env_names: Vec<&str> = env.iter().map(|(name, _)| name.as_str()).collect();
+        cprintln!(
+            "<s,c>»</> Running <s>{program}</> with <s>{}</>...",
+            env_names.join(", ")
+        );
+    }
+
+    let mut command = std::process::Command::new(program);
+    command.args(args).envs(env);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // only returns on failure:
+        let err = command.exec();
+        tracing::error!("failed to run `{program}`: {err}");
+        Err(EX_UNAVAILABLE)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = command.status().map_err(|e| {
+            tracing::error!("failed to run `{program}`: {e}");
+            EX_UNAVAILABLE
+        })?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
@@ -4,31 +4,39 @@ use crate::{
     StandardOptions,
     SysexitsError::{self, *},
 };
-use asimov_installer::InstallOptions;
-use asimov_module::{ConfigurationVariable, InstalledModuleManifest, ModuleManifest, ReadVarError};
+use crate::{
+    installer::InstallOptions,
+    policy::Channel,
+    profiles::DEFAULT_PROFILE,
+    reasons::{InstallReason, InstallReasons},
+    selectors::Selection,
+    variables::Variables,
+};
+use asimov_module::{ConfigurationVariable, InstalledModuleManifest, ModuleManifest};
 use color_print::{ceprintln, cprintln};
 
 #[tokio::main]
+#[allow(clippy::too_many_arguments)]
 pub async fn install(
-    mut module_names: Vec<String>,
+    mut selection: Selection,
     version: Option<String>,
     model_size: Option<String>,
+    force: bool,
+    no_prompt: bool,
+    pre: bool,
+    profile: Option<String>,
     flags: &StandardOptions,
 ) -> Result<(), SysexitsError> {
     let registry = asimov_registry::Registry::default();
-    let installer = asimov_installer::Installer::default();
+    let installer = crate::installer::Installer::default().with_pre(pre);
+    let profile = super::profile::resolve_profile(profile.as_deref()).await?;
+    warn_unread_profile(&profile);
 
-    let install_options = InstallOptions::builder()
-        .maybe_version(version.clone())
-        .maybe_model_size(model_size)
-        .build();
-
-    if module_names.len() == 1 && module_names[0] == "all" {
-        module_names = fetch_all_module_names().await.map_err(|e| {
-            tracing::error!("unable to fetch list of all modules: {e}");
-            EX_UNAVAILABLE
-        })?;
+    if selection.names.len() == 1 && selection.names[0] == "all" {
+        selection.names.clear();
+        selection.all = true;
     }
+    let module_names = selection.resolve_catalog(&installer).await?;
 
     for module_name in module_names {
         if !registry
@@ -40,7 +48,7 @@ pub async fn install(
                 want.clone()
             } else {
                 installer
-                    .fetch_latest_release(&module_name)
+                    .fetch_latest_for(&module_name)
                     .await
                     .map_err(|e| {
                         tracing::error!(
@@ -60,8 +68,13 @@ pub async fn install(
                 cprintln!("<s,c>»</> Installing module <s>{module_name}</>...");
             }
 
+            let install_options = InstallOptions::builder()
+                .version(target_version)
+                .maybe_model_size(model_size.clone())
+                .build();
+
             installer
-                .install_module(module_name.clone(), &install_options)
+                .install_module(&module_name, &install_options)
                 .await
                 .map_err(|e| {
                     tracing::error!("failed to install module `{module_name}`: {e}");
@@ -71,10 +84,29 @@ pub async fn install(
             if flags.verbose > 0 {
                 cprintln!("<s,g>✓</> Installed module <s>{module_name}</>.");
             }
+        } else if force {
+            super::reinstall::reinstall_module(
+                &installer,
+                &module_name,
+                version.clone(),
+                false,
+                model_size.clone(),
+                flags,
+            )
+            .await?;
         } else if flags.verbose > 0 {
             cprintln!("<s,g>✓</> Module <s>{module_name}</> is already installed.");
         }
 
+        // Explicitly installed modules are kept by `autoremove`:
+        InstallReasons::default()
+            .set(&module_name, InstallReason::Manual)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to record install reason of `{module_name}`: {e}");
+                EX_IOERR
+            })?;
+
         if registry
             .is_module_enabled(&module_name)
             .await
@@ -88,31 +120,8 @@ pub async fn install(
             EX_UNAVAILABLE
         })?;
 
-        let variables = manifest
-            .manifest
-            .config
-            .iter()
-            .flat_map(|conf| conf.variables.iter());
-
-        let mut missing_variables = Vec::new();
-        for var in variables {
-            if var.default_value.is_some() {
-                continue;
-            }
-            match manifest.manifest.variable(&var.name, None) {
-                Ok(_) => (),
-                Err(ReadVarError::UnconfiguredVar(_)) => {
-                    missing_variables.push(var);
-                },
-                Err(e) => {
-                    tracing::error!(
-                        "failed to read configuration variable `{}` for module `{module_name}`: {e}",
-                        var.name
-                    );
-                    return Err(EX_UNAVAILABLE);
-                },
-            }
-        }
+        let missing_variables =
+            check_configuration(&module_name, &manifest.manifest, !no_prompt).await?;
 
         if missing_variables.is_empty() {
             registry.enable_module(&module_name).await.map_err(|e| {
@@ -123,34 +132,180 @@ pub async fn install(
             ceprintln!(
                 "<s,y>warn:</> Module <s>{module_name}</> wasn't enabled automatically due to missing configuration."
             );
-            ceprintln!("<s,dim>hint:</> Module <s>{module_name}</> requires configuration:");
-
-            for var in missing_variables {
-                let desc_suffix = if let Some(ref desc) = var.description {
-                    format!(" (Description: \"{desc}\")")
-                } else {
-                    String::new()
-                };
-
-                ceprintln!(
-                    "<s,dim>hint:</>   Missing variable: <s>{}</s>{}",
-                    var.name,
-                    desc_suffix
-                );
+            print_missing_variables(&module_name, &missing_variables, &profile);
+        }
+    }
 
-                if let Some(ref env) = var.environment {
-                    ceprintln!(
-                        "<s,dim>hint:</>   Alternative: set environment variable: <s>{env}</>"
-                    );
-                }
-            }
+    Ok(())
+}
+
+/// Returns the release channel to follow for the module, which is `dev` if
+/// pre-releases were requested, and otherwise the configured channel.
+pub(crate) async fn module_channel(module_name: &str, pre: bool) -> Result<Channel, SysexitsError> {
+    if pre {
+        return Ok(Channel::Dev);
+    }
+    crate::settings::Settings::default()
+        .channel(module_name)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to read release channel of `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })
+}
+
+/// Warns that the profile in use doesn't apply to the modules themselves,
+/// which only read the `default` profile.
+pub(crate) fn warn_unread_profile(profile: &str) {
+    if profile != DEFAULT_PROFILE {
+        ceprintln!(
+            "<s,y>warn:</> Modules only read the <s>{DEFAULT_PROFILE}</> profile, so their configuration is checked there rather than in profile <s>{profile}</>."
+        );
+    }
+}
+
+/// Returns the required configuration variables the module can't read a
+/// value for, first offering to configure them when `prompt` is set and
+/// running on a terminal.
+pub(crate) async fn check_configuration(
+    module_name: &str,
+    manifest: &ModuleManifest,
+    prompt: bool,
+) -> Result<Vec<MissingVariable>, SysexitsError> {
+    let missing_variables = find_missing_variables(module_name, manifest).await?;
+    // Configuring again won't help with encrypted values:
+    if missing_variables.iter().all(|missing| missing.encrypted)
+        || !prompt
+        || !crate::is_interactive()
+    {
+        return Ok(missing_variables);
+    }
+
+    ceprintln!(
+        "<s,y>warn:</> Module <s>{module_name}</> requires configuration before it can be enabled."
+    );
+    if !crate::confirm(&format!("Configure module `{module_name}` now?"), true)? {
+        return Ok(missing_variables);
+    }
+
+    super::config::prompt_variables(module_name, manifest, DEFAULT_PROFILE).await?;
+    super::config::run_configurator(module_name, manifest, DEFAULT_PROFILE).await?;
+    find_missing_variables(module_name, manifest).await
+}
+
+/// A required configuration variable that the module can't read a value for.
+#[derive(Clone, Debug)]
+pub(crate) struct MissingVariable {
+    pub var: ConfigurationVariable,
+    /// Whether the value is stored encrypted, which the module only receives
+    /// through its environment variable.
+    pub encrypted: bool,
+}
+
+/// Returns the configuration variables of the module that have neither a
+/// value in their environment variable, a plain-text value in the `default`
+/// profile, which is the only one modules read, nor a default value.
+pub(crate) async fn find_missing_variables(
+    module_name: &str,
+    manifest: &ModuleManifest,
+) -> Result<Vec<MissingVariable>, SysexitsError> {
+    let variables = manifest
+        .config
+        .iter()
+        .flat_map(|conf| conf.variables.iter());
 
-            ceprintln!("<s,dim>hint:</>   To configure: <s>asimov module config {module_name}</s>");
-            ceprintln!("<s,dim>hint:</>   To enable: <s>asimov module enable {module_name}</s>");
+    let read_error = |var: &ConfigurationVariable, e: std::io::Error| {
+        tracing::error!(
+            "failed to read configuration variable `{}` for module `{module_name}`: {e}",
+            var.name
+        );
+        EX_UNAVAILABLE
+    };
+
+    let mut missing_variables = Vec::new();
+    for var in variables {
+        if var.default_value.is_some()
+            || var
+                .environment
+                .as_deref()
+                .is_some_and(|env_name| std::env::var_os(env_name).is_some())
+        {
+            continue;
+        }
+        let stored = Variables::default();
+        let is_stored = stored
+            .is_stored(DEFAULT_PROFILE, module_name, &var.name)
+            .await
+            .map_err(|e| read_error(var, e))?;
+        let encrypted = stored
+            .is_encrypted(DEFAULT_PROFILE, module_name, &var.name)
+            .await
+            .map_err(|e| read_error(var, e))?;
+        if !is_stored || encrypted {
+            missing_variables.push(MissingVariable {
+                var: var.clone(),
+                encrypted,
+            });
         }
     }
 
-    Ok(())
+    Ok(missing_variables)
+}
+
+/// Prints hints on how to configure the missing variables and then enable
+/// the module, given the profile in use.
+pub(crate) fn print_missing_variables(
+    module_name: &str,
+    missing_variables: &[MissingVariable],
+    profile: &str,
+) {
+    ceprintln!("<s,dim>hint:</> Module <s>{module_name}</> requires configuration:");
+
+    for MissingVariable { var, encrypted } in missing_variables {
+        let desc_suffix = if let Some(ref desc) = var.description {
+            format!(" (Description: \"{desc}\")")
+        } else {
+            String::new()
+        };
+
+        if *encrypted {
+            ceprintln!(
+                "<s,dim>hint:</>   Encrypted variable: <s>{}</s>{}, which the module can't read itself",
+                var.name,
+                desc_suffix
+            );
+        } else {
+            ceprintln!(
+                "<s,dim>hint:</>   Missing variable: <s>{}</s>{}",
+                var.name,
+                desc_suffix
+            );
+        }
+
+        if let Some(ref env) = var.environment {
+            ceprintln!("<s,dim>hint:</>   Alternative: set environment variable: <s>{env}</>");
+        }
+    }
+
+    if missing_variables.iter().any(|missing| missing.encrypted) {
+        ceprintln!(
+            "<s,dim>hint:</>   To pass encrypted values: <s>asimov module exec {module_name} -- <<command>></s>"
+        );
+        ceprintln!(
+            "<s,dim>hint:</>   To enable: <s>asimov module exec {module_name} -- asimov module enable {module_name}</s>"
+        );
+        return;
+    }
+
+    let profile_flag = if profile == DEFAULT_PROFILE {
+        String::new()
+    } else {
+        format!("--profile {DEFAULT_PROFILE} ")
+    };
+    ceprintln!(
+        "<s,dim>hint:</>   To configure: <s>asimov module {profile_flag}config {module_name}</s>"
+    );
+    ceprintln!("<s,dim>hint:</>   To enable: <s>asimov module enable {module_name}</s>");
 }
 
 pub async fn fetch_all_module_names() -> Result<Vec<String>, Box<dyn core::error::Error>> {
@@ -3,12 +3,14 @@
 use crate::{
     StandardOptions,
     SysexitsError::{self, *},
+    reasons::{InstallReason, InstallReasons},
 };
 use color_print::cprintln;
 
 #[tokio::main]
 pub async fn list(output: &str, flags: &StandardOptions) -> Result<(), SysexitsError> {
     let registry = asimov_registry::Registry::default();
+    let reasons = InstallReasons::default();
     let modules = registry.installed_modules().await.map_err(|e| {
         tracing::error!("failed to read installed modules: {e}");
         EX_UNAVAILABLE
@@ -20,6 +22,10 @@ pub async fn list(output: &str, flags: &StandardOptions) -> Result<(), SysexitsE
             tracing::error!("failed to check if module is enabled: {e}");
             EX_UNAVAILABLE
         })?;
+        let reason = reasons.get(&name).await.map_err(|e| {
+            tracing::error!("failed to read install reason of module: {e}");
+            EX_UNAVAILABLE
+        })?;
 
         match output {
             "jsonl" => {
@@ -27,15 +33,19 @@ pub async fn list(output: &str, flags: &StandardOptions) -> Result<(), SysexitsE
                 let label = module.manifest.label;
                 let uri = format!("https://asimov.directory/modules/{}", name);
                 println!(
-                    r#"{{"@type": "{}", "@id": "{}", "name": "{}", "label": "{}", "enabled": {}, "version": "{}"}}"#,
-                    "AsimovModule", uri, name, label, is_enabled, version
+                    r#"{{"@type": "AsimovModule", "@id": "{}", "name": "{}", "label": "{}", "enabled": {}, "version": "{}", "reason": "{}"}}"#,
+                    uri, name, label, is_enabled, version, reason
                 );
             },
-            "cli" | _ => {
+            _ => {
+                let suffix = match reason {
+                    InstallReason::Auto => " (dependency)",
+                    InstallReason::Manual => "",
+                };
                 if is_enabled {
-                    cprintln!("<s,g>✓</> {}", name);
+                    cprintln!("<s,g>✓</> {}<dim>{}</>", name, suffix);
                 } else {
-                    cprintln!("<s,r>✗</> {}", name);
+                    cprintln!("<s,r>✗</> {}<dim>{}</>", name, suffix);
                 }
             },
         }
@@ -0,0 +1,40 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    reasons::{InstallReason, InstallReasons},
+};
+use color_print::{ceprintln, cprintln};
+
+#[tokio::main]
+pub async fn mark(
+    module_names: Vec<String>,
+    reason: InstallReason,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+    let reasons = InstallReasons::default();
+
+    for module_name in module_names {
+        if !registry
+            .is_module_installed(&module_name)
+            .await
+            .unwrap_or(false)
+        {
+            ceprintln!("<s,r>error:</> Module <s>{module_name}</> is not installed.");
+            return Err(EX_UNAVAILABLE);
+        }
+
+        reasons.set(&module_name, reason).await.map_err(|e| {
+            tracing::error!("failed to mark module `{module_name}`: {e}");
+            EX_IOERR
+        })?;
+
+        if flags.verbose > 0 {
+            cprintln!("<s,g>✓</> Marked module <s>{module_name}</> as {reason}.");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,180 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    format_bytes,
+    models::{ModelFile, module_models},
+};
+use color_print::{ceprintln, cprintln};
+
+#[tokio::main]
+pub async fn models_list(
+    module_name: String,
+    _flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let models = read_module_models(&module_name).await?;
+
+    let client = crate::registry::http::http_client();
+    let download_sizes = futures::future::join_all(
+        models
+            .iter()
+            .map(|model| model.fetch_download_size(&client)),
+    )
+    .await;
+
+    let mut current_repo = None;
+    let mut total_size = 0;
+    for (model, download_size) in models.iter().zip(download_sizes) {
+        if current_repo != Some(&model.repo) {
+            cprintln!("<s>hf:{}</>", model.repo);
+            current_repo = Some(&model.repo);
+        }
+
+        let size = model.size.as_deref().unwrap_or("-");
+        let download_size = download_size.map(format_bytes).unwrap_or("?".into());
+        if let Some(local_size) = model.local_size() {
+            total_size += local_size;
+            cprintln!(
+                "  <s,g>✓</> {size:<10} {:<40} {download_size:>10} <dim>(downloaded, {})</>",
+                model.filename,
+                format_bytes(local_size)
+            );
+        } else {
+            cprintln!(
+                "  <s,r>✗</> {size:<10} {:<40} {download_size:>10}",
+                model.filename
+            );
+        }
+    }
+
+    if !models.is_empty() {
+        cprintln!("<s>Total downloaded:</> {}", format_bytes(total_size));
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn models_pull(
+    module_name: String,
+    size: Option<String>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let models = read_module_models(&module_name).await?;
+    let models = select_models(&module_name, &models, size.as_deref(), true)?;
+
+    for model in models {
+        if flags.verbose > 1 {
+            cprintln!("<s,c>»</> Downloading model <s>{}</>...", model.filename);
+        }
+
+        model.pull().map_err(|e| {
+            tracing::error!("failed to download model `{}`: {e}", model.filename);
+            EX_UNAVAILABLE
+        })?;
+
+        if flags.verbose > 0 {
+            cprintln!("<s,g>✓</> Downloaded model <s>{}</>.", model.filename);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn models_remove(
+    module_name: String,
+    size: Option<String>,
+    _flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let models = read_module_models(&module_name).await?;
+    let models = select_models(&module_name, &models, size.as_deref(), false)?;
+
+    for model in models {
+        if model.local_path().is_none() {
+            continue;
+        }
+
+        let freed = model.remove().map_err(|e| {
+            tracing::error!("failed to remove model `{}`: {e}", model.filename);
+            EX_IOERR
+        })?;
+
+        cprintln!(
+            "<s,g>✓</> Removed model <s>{}</>, freeing {}.",
+            model.filename,
+            format_bytes(freed)
+        );
+    }
+
+    Ok(())
+}
+
+async fn read_module_models(module_name: &str) -> Result<Vec<ModelFile>, SysexitsError> {
+    let manifest = asimov_registry::Registry::default()
+        .read_manifest(module_name)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to read manifest for module `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })?
+        .manifest;
+
+    let models = module_models(&manifest);
+    if models.is_empty() {
+        ceprintln!("<s,y>warn:</> Module <s>{module_name}</> doesn't require any models.");
+    }
+    Ok(models)
+}
+
+/// Selects the model files of the given size, along with models that don't
+/// have size choices when pulling. Without a size, selects all models when
+/// removing, or for each model either an already downloaded choice or the
+/// first choice when pulling.
+fn select_models<'a>(
+    module_name: &str,
+    models: &'a [ModelFile],
+    size: Option<&str>,
+    pulling: bool,
+) -> Result<Vec<&'a ModelFile>, SysexitsError> {
+    let selected: Vec<_> = match size {
+        Some(size) => {
+            if !models.iter().any(|m| m.size.as_deref() == Some(size)) {
+                ceprintln!(
+                    "<s,r>error:</> Module <s>{module_name}</> has no model size <s>{size}</>."
+                );
+                ceprintln!(
+                    "<s,dim>hint:</> List the available sizes with: <s>asimov module models list {module_name}</>"
+                );
+                return Err(EX_USAGE);
+            }
+            models
+                .iter()
+                .filter(|m| m.size.as_deref() == Some(size) || (pulling && m.size.is_none()))
+                .collect()
+        },
+        None if pulling => {
+            let mut selected: Vec<&ModelFile> = Vec::new();
+            for model in models {
+                if model.size.is_none() {
+                    selected.push(model);
+                    continue;
+                }
+                if selected.iter().any(|m| m.repo == model.repo) {
+                    continue;
+                }
+                // prefer a size choice which is already downloaded
+                let choice = models
+                    .iter()
+                    .filter(|m| m.repo == model.repo && m.size.is_some())
+                    .find(|m| m.local_path().is_some())
+                    .unwrap_or(model);
+                selected.push(choice);
+            }
+            selected
+        },
+        None => models.iter().collect(),
+    };
+    Ok(selected)
+}
@@ -0,0 +1,86 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    installer::Installer,
+    policy::parse_version,
+};
+use color_print::{ceprintln, cprintln};
+use futures::StreamExt;
+
+/// The number of latest-version lookups done at the same time.
+const LOOKUP_CONCURRENCY: usize = 8;
+
+#[tokio::main]
+pub async fn outdated(pre: bool, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let installer = Installer::default();
+
+    let installed = asimov_registry::Registry::default()
+        .installed_modules()
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to read installed modules: {e}");
+            EX_UNAVAILABLE
+        })?;
+
+    let lookups: Vec<_> = futures::stream::iter(installed)
+        .map(|manifest| {
+            let installer = &installer;
+            async move {
+                let module_name = manifest.manifest.name;
+                let latest = match super::install::module_channel(&module_name, pre).await {
+                    Ok(channel) => installer
+                        .fetch_latest_on_channel(&module_name, channel)
+                        .await
+                        .map(|latest| (latest, channel))
+                        .map_err(|e| e.to_string()),
+                    Err(_) => Err("failed to read release channel".into()),
+                };
+                (module_name, manifest.version, latest)
+            }
+        })
+        .buffered(LOOKUP_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut failed = false;
+    let mut any_outdated = false;
+    for (module_name, current, latest) in lookups {
+        let (latest, channel) = match latest {
+            Ok(latest) => latest,
+            Err(e) => {
+                failed = true;
+                ceprintln!(
+                    "<s,r>error:</> unable to find latest release for module <s>{module_name}</>: {e}"
+                );
+                continue;
+            },
+        };
+
+        let is_outdated = match (
+            current.as_deref().and_then(parse_version),
+            parse_version(&latest),
+        ) {
+            (Some(current), Some(latest)) => latest > current,
+            _ => current.as_deref() != Some(latest.as_str()),
+        };
+        if !is_outdated {
+            continue;
+        }
+
+        any_outdated = true;
+        let current = current.as_deref().unwrap_or("unknown");
+        cprintln!("<s>{module_name}</>: {current} → <s>{latest}</> <dim>({channel})</>");
+    }
+
+    if !any_outdated && !failed && flags.verbose > 0 {
+        cprintln!("<s,g>✓</> All installed modules are up to date.");
+    }
+
+    if failed {
+        return Err(EX_UNAVAILABLE);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    pins::Pins,
+};
+use asimov_registry::error::ManifestError;
+use color_print::{ceprintln, cprintln};
+
+#[tokio::main]
+pub async fn pin(module_specs: Vec<String>, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+    let pins = Pins::default();
+
+    if module_specs.is_empty() {
+        let pins = pins.list().await.map_err(|e| {
+            tracing::error!("failed to read pinned modules: {e}");
+            EX_UNAVAILABLE
+        })?;
+        for pin in pins {
+            let version = pin.version.as_deref().unwrap_or("installed version");
+            cprintln!("<s>{}</> {version}", pin.module);
+        }
+        return Ok(());
+    }
+
+    for spec in module_specs {
+        let (module_name, version) = match spec.split_once('@') {
+            Some((name, version)) => (name.to_string(), Some(version.to_string())),
+            None => (spec, None),
+        };
+
+        let installed = match registry.read_manifest(&module_name).await {
+            Ok(manifest) => manifest.version,
+            Err(ManifestError::NotInstalled) if version.is_some() => None,
+            Err(ManifestError::NotInstalled) => {
+                ceprintln!("<s,r>error:</> Module <s>{module_name}</> is not installed.");
+                ceprintln!(
+                    "<s,dim>hint:</> Pin a specific version with: <s>asimov module pin {module_name}@<<version>></>"
+                );
+                return Err(EX_UNAVAILABLE);
+            },
+            Err(e) => {
+                tracing::error!("failed to read installed version of `{module_name}`: {e}");
+                return Err(EX_UNAVAILABLE);
+            },
+        };
+        let version = version.or(installed.clone());
+
+        pins.pin(&module_name, version.as_deref())
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to pin module `{module_name}`: {e}");
+                EX_IOERR
+            })?;
+
+        if flags.verbose > 0 {
+            match version {
+                Some(ref version) => {
+                    cprintln!("<s,g>✓</> Pinned module <s>{module_name}</> at <s>{version}</>.")
+                },
+                None => cprintln!("<s,g>✓</> Pinned module <s>{module_name}</>."),
+            }
+        }
+
+        if let (Some(installed), Some(version)) = (installed, version)
+            && installed != version
+        {
+            ceprintln!(
+                "<s,y>warn:</> Module <s>{module_name}</> is installed at version <s>{installed}</>."
+            );
+            ceprintln!(
+                "<s,dim>hint:</> Move to the pinned version with: <s>asimov module reinstall {module_name} --version {version}</>"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn unpin(
+    module_names: Vec<String>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let pins = Pins::default();
+
+    for module_name in module_names {
+        let was_pinned = pins.unpin(&module_name).await.map_err(|e| {
+            tracing::error!("failed to unpin module `{module_name}`: {e}");
+            EX_IOERR
+        })?;
+
+        if !was_pinned {
+            ceprintln!("<s,y>warn:</> Module <s>{module_name}</> is not pinned.");
+        } else if flags.verbose > 0 {
+            cprintln!("<s,g>✓</> Unpinned module <s>{module_name}</>.");
+        }
+    }
+
+    Ok(())
+}
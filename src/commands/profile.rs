@@ -0,0 +1,166 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    profiles::{DEFAULT_PROFILE, Profiles, is_valid_name},
+};
+use color_print::{ceprintln, cprintln};
+
+#[tokio::main]
+pub async fn profile_list(
+    profile: Option<String>,
+    _flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let profiles = Profiles::default();
+    let current = profiles.resolve(profile.as_deref()).await.map_err(|e| {
+        tracing::error!("failed to read the selected profile: {e}");
+        EX_IOERR
+    })?;
+    let names = profiles.list().await.map_err(|e| {
+        tracing::error!("failed to read profiles: {e}");
+        EX_IOERR
+    })?;
+
+    for name in names {
+        if name == current {
+            cprintln!("<s,g>*</> <s>{name}</>");
+        } else {
+            println!("  {name}");
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn profile_create(name: String, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let profiles = Profiles::default();
+    check_name(&name)?;
+    if exists(&profiles, &name).await? {
+        ceprintln!("<s,r>error:</> Profile <s>{name}</> already exists.");
+        return Err(EX_USAGE);
+    }
+
+    profiles.create(&name).await.map_err(|e| {
+        tracing::error!("failed to create profile `{name}`: {e}");
+        EX_IOERR
+    })?;
+
+    if flags.verbose > 0 {
+        cprintln!("<s,g>✓</> Created profile <s>{name}</>.");
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn profile_copy(
+    from: String,
+    to: String,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let profiles = Profiles::default();
+    check_name(&to)?;
+    if !exists(&profiles, &from).await? {
+        return Err(unknown_profile(&from));
+    }
+    if exists(&profiles, &to).await? {
+        ceprintln!("<s,r>error:</> Profile <s>{to}</> already exists.");
+        return Err(EX_USAGE);
+    }
+
+    profiles.copy(&from, &to).await.map_err(|e| {
+        tracing::error!("failed to copy profile `{from}` to `{to}`: {e}");
+        EX_IOERR
+    })?;
+
+    if flags.verbose > 0 {
+        cprintln!("<s,g>✓</> Copied profile <s>{from}</> to <s>{to}</>.");
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn profile_delete(name: String, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let profiles = Profiles::default();
+    if name == DEFAULT_PROFILE {
+        ceprintln!("<s,r>error:</> The default profile can't be deleted.");
+        return Err(EX_USAGE);
+    }
+    if !exists(&profiles, &name).await? {
+        return Err(unknown_profile(&name));
+    }
+
+    profiles.delete(&name).await.map_err(|e| {
+        tracing::error!("failed to delete profile `{name}`: {e}");
+        EX_IOERR
+    })?;
+
+    if flags.verbose > 0 {
+        cprintln!("<s,g>✓</> Deleted profile <s>{name}</>.");
+    }
+    Ok(())
+}
+
+#[tokio::main]
+pub async fn profile_use(name: String, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let profiles = Profiles::default();
+    if !exists(&profiles, &name).await? {
+        return Err(unknown_profile(&name));
+    }
+
+    profiles.set_active(&name).await.map_err(|e| {
+        tracing::error!("failed to select profile `{name}`: {e}");
+        EX_IOERR
+    })?;
+
+    if flags.verbose > 0 {
+        cprintln!("<s,g>✓</> Using profile <s>{name}</>.");
+    }
+    if name != DEFAULT_PROFILE {
+        ceprintln!(
+            "<s,y>warn:</> Modules keep reading their configuration from the <s>{DEFAULT_PROFILE}</> profile, only the <s>asimov module config</> commands use profile <s>{name}</>."
+        );
+        ceprintln!(
+            "<s,dim>hint:</> To apply it, export it and import it into <s>{DEFAULT_PROFILE}</>: <s>asimov module config export -o {name}.json</> and <s>asimov module --profile {DEFAULT_PROFILE} config import {name}.json</>"
+        );
+    }
+    Ok(())
+}
+
+/// Returns the profile to use, given the `--profile` flag, failing if it
+/// doesn't exist.
+pub(crate) async fn resolve_profile(profile: Option<&str>) -> Result<String, SysexitsError> {
+    let profiles = Profiles::default();
+    let profile = profiles.resolve(profile).await.map_err(|e| {
+        tracing::error!("failed to read the selected profile: {e}");
+        EX_IOERR
+    })?;
+    check_name(&profile)?;
+    if !exists(&profiles, &profile).await? {
+        return Err(unknown_profile(&profile));
+    }
+    Ok(profile)
+}
+
+fn check_name(name: &str) -> Result<(), SysexitsError> {
+    if !is_valid_name(name) {
+        ceprintln!(
+            "<s,r>error:</> invalid profile name: `{name}`, use letters, digits, `-`, `_` and `.`"
+        );
+        return Err(EX_USAGE);
+    }
+    Ok(())
+}
+
+async fn exists(profiles: &Profiles, name: &str) -> Result<bool, SysexitsError> {
+    profiles.exists(name).await.map_err(|e| {
+        tracing::error!("failed to check for profile `{name}`: {e}");
+        EX_IOERR
+    })
+}
+
+fn unknown_profile(name: &str) -> SysexitsError {
+    ceprintln!("<s,r>error:</> Profile <s>{name}</> doesn't exist.");
+    ceprintln!("<s,dim>hint:</> Create it with: <s>asimov module profile create {name}</>");
+    EX_USAGE
+}
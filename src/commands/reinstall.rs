@@ -0,0 +1,99 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    installer::{InstallOptions, Installer},
+};
+use color_print::cprintln;
+
+#[tokio::main]
+pub async fn reinstall(
+    module_names: Vec<String>,
+    version: Option<String>,
+    keep_version: bool,
+    model_size: Option<String>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let installer = Installer::default();
+
+    for module_name in module_names {
+        reinstall_module(
+            &installer,
+            &module_name,
+            version.clone(),
+            keep_version,
+            model_size.clone(),
+            flags,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Reinstalls a single module, optionally pinned to the currently installed
+/// version.
+pub(crate) async fn reinstall_module(
+    installer: &Installer,
+    module_name: &str,
+    version: Option<String>,
+    keep_version: bool,
+    model_size: Option<String>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+
+    let version = if version.is_none() && keep_version {
+        let current = registry
+            .module_version(module_name)
+            .await
+            .inspect_err(|e| {
+                tracing::warn!("unable to read installed version of `{module_name}`: {e}")
+            })
+            .ok()
+            .flatten();
+        if current.is_none() {
+            tracing::warn!(
+                "unknown installed version for module `{module_name}`, reinstalling latest version"
+            );
+        }
+        current
+    } else {
+        version
+    };
+
+    let target_version = if let Some(version) = version {
+        version
+    } else {
+        installer.fetch_latest_for(module_name).await.map_err(|e| {
+            tracing::error!("unable to find latest release for module `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })?
+    };
+
+    if flags.verbose > 1 {
+        cprintln!("<s,c>»</> Reinstalling module <s>{module_name}</>...");
+    }
+
+    let install_options = InstallOptions::builder()
+        .version(target_version.clone())
+        .maybe_model_size(model_size)
+        .build();
+
+    installer
+        .reinstall_module(module_name, &install_options)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to reinstall module `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })?;
+
+    if flags.verbose > 0 {
+        cprintln!(
+            "<s,g>✓</> Reinstalled module <s>{module_name}</> at version <s>{target_version}</>."
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    installer::{Installer, error::RollbackError},
+};
+use color_print::{ceprintln, cprintln};
+use std::time::SystemTime;
+
+#[tokio::main]
+pub async fn rollback(
+    module_name: String,
+    version: Option<String>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let installer = Installer::default();
+
+    if flags.verbose > 1 {
+        cprintln!("<s,c>»</> Rolling back module <s>{module_name}</>...");
+    }
+
+    match installer
+        .rollback_module(&module_name, version.as_deref())
+        .await
+    {
+        Ok(version) => {
+            cprintln!(
+                "<s,g>✓</> Rolled back module <s>{module_name}</> to version <s>{version}</>."
+            );
+            Ok(())
+        },
+        Err(RollbackError::NothingKept) => {
+            ceprintln!("<s,r>error:</> No previous version of module <s>{module_name}</> is kept.");
+            Err(EX_UNAVAILABLE)
+        },
+        Err(RollbackError::NoSuchVersion(version)) => {
+            ceprintln!(
+                "<s,r>error:</> Version <s>{version}</> of module <s>{module_name}</> is not kept."
+            );
+            ceprintln!(
+                "<s,dim>hint:</> List the kept versions with: <s>asimov module rollback --list {module_name}</>"
+            );
+            Err(EX_USAGE)
+        },
+        Err(e) => {
+            tracing::error!("failed to roll back module `{module_name}`: {e}");
+            Err(EX_UNAVAILABLE)
+        },
+    }
+}
+
+#[tokio::main]
+pub async fn rollback_list(
+    module_name: Option<String>,
+    _flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let registry = asimov_registry::Registry::default();
+    let installer = Installer::default();
+    let versions = installer.versions();
+
+    let module_names = match module_name {
+        Some(module_name) => vec![module_name],
+        None => versions.modules().await.map_err(|e| {
+            tracing::error!("failed to read kept versions: {e}");
+            EX_UNAVAILABLE
+        })?,
+    };
+
+    for module_name in module_names {
+        let kept = versions.list(&module_name).await.map_err(|e| {
+            tracing::error!("failed to read kept versions of `{module_name}`: {e}");
+            EX_UNAVAILABLE
+        })?;
+        if kept.is_empty() {
+            continue;
+        }
+
+        let current = registry.module_version(&module_name).await.ok().flatten();
+        match current {
+            Some(current) => cprintln!("<s>{module_name}</> <dim>(installed: {current})</>"),
+            None => cprintln!("<s>{module_name}</>"),
+        }
+        for kept in kept {
+            let age = kept
+                .kept_at
+                .and_then(|kept_at| SystemTime::now().duration_since(kept_at).ok())
+                .map(|age| format_age(age.as_secs()))
+                .unwrap_or("?".into());
+            cprintln!("  {:<20} <dim>kept {age}</>", kept.version);
+        }
+    }
+
+    Ok(())
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => "just now".into(),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
@@ -0,0 +1,104 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    installer::{
+        Installer,
+        error::SelfUpdateError,
+        platform::detect_platform,
+        self_update::{rollback_cli, rollback_path},
+    },
+    policy::parse_version,
+};
+use color_print::{ceprintln, cprintln};
+use std::path::PathBuf;
+
+#[tokio::main]
+pub async fn self_update(
+    check: bool,
+    rollback: bool,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let current = env!("CARGO_PKG_VERSION");
+    let exe = current_exe()?;
+
+    if rollback {
+        if !rollback_path(&exe).exists() {
+            ceprintln!("<s,r>error:</> No previous version of <s>asimov-module</> is kept.");
+            return Err(EX_UNAVAILABLE);
+        }
+        rollback_cli(&exe).await.map_err(|e| {
+            tracing::error!("failed to roll back `{}`: {e}", exe.display());
+            permission_hint(&e);
+            EX_IOERR
+        })?;
+        cprintln!("<s,g>✓</> Restored the previous version of <s>asimov-module</>.");
+        return Ok(());
+    }
+
+    let installer = Installer::default();
+    let latest = installer.fetch_latest_cli_version().await.map_err(|e| {
+        tracing::error!("unable to find latest release of asimov-module: {e}");
+        EX_UNAVAILABLE
+    })?;
+
+    let is_newer = match (parse_version(current), parse_version(&latest)) {
+        (Some(current), Some(latest)) => latest > current,
+        _ => current != latest.trim_start_matches('v'),
+    };
+    if !is_newer {
+        if flags.verbose > 0 {
+            cprintln!("<s,g>✓</> <s>asimov-module</> is up to date at version <s>{current}</>.");
+        }
+        return Ok(());
+    }
+
+    if check {
+        cprintln!("<s>asimov-module</>: {current} → <s>{latest}</>");
+        return Err(EX_TEMPFAIL);
+    }
+
+    if flags.verbose > 1 {
+        cprintln!(
+            "<s,c>»</> Updating <s>{}</> to version <s>{latest}</>...",
+            exe.display()
+        );
+    }
+
+    installer
+        .update_cli(&latest, &detect_platform(), &exe)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to update asimov-module: {e}");
+            if let SelfUpdateError::Replace(ref e) = e {
+                permission_hint(e);
+            }
+            EX_UNAVAILABLE
+        })?;
+
+    cprintln!("<s,g>✓</> Updated <s>asimov-module</> from version {current} to <s>{latest}</>.");
+    ceprintln!(
+        "<s,dim>hint:</> Restore the previous version with: <s>asimov module self-update --rollback</>"
+    );
+
+    Ok(())
+}
+
+fn current_exe() -> Result<PathBuf, SysexitsError> {
+    // Resolve symlinks, e.g., from a package manager's bin directory:
+    std::env::current_exe()
+        .and_then(|exe| exe.canonicalize())
+        .map_err(|e| {
+            tracing::error!("unable to locate the running binary: {e}");
+            EX_OSERR
+        })
+}
+
+fn permission_hint(error: &std::io::Error) {
+    if error.kind() == std::io::ErrorKind::PermissionDenied {
+        ceprintln!(
+            "<s,dim>hint:</> If installed with a package manager, such as Homebrew or Scoop, update through it instead."
+        );
+    }
+}
@@ -0,0 +1,86 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    StandardOptions,
+    SysexitsError::{self, *},
+    settings::{KNOWN_SETTINGS, Settings, allowed_values},
+};
+use color_print::{ceprintln, cprintln};
+
+#[tokio::main]
+pub async fn settings(
+    key: Option<String>,
+    value: Option<String>,
+    module_name: Option<String>,
+    unset: bool,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let settings = Settings::default();
+    let module_name = module_name.as_deref();
+
+    let Some(key) = key else {
+        let all = settings.list().await.map_err(|e| {
+            tracing::error!("failed to read settings: {e}");
+            EX_UNAVAILABLE
+        })?;
+        for setting in all {
+            match setting.module {
+                Some(module) => cprintln!("<s>{module}</>.{} = {}", setting.key, setting.value),
+                None => cprintln!("{} = {}", setting.key, setting.value),
+            }
+        }
+        return Ok(());
+    };
+
+    let Some(allowed) = allowed_values(&key) else {
+        ceprintln!("<s,r>error:</> unrecognized setting: `{key}`");
+        let known: Vec<_> = KNOWN_SETTINGS.iter().map(|(name, _)| *name).collect();
+        ceprintln!("<s,dim>hint:</> Known settings: {}", known.join(", "));
+        return Err(EX_USAGE);
+    };
+
+    if unset {
+        let was_set = settings.unset(module_name, &key).await.map_err(|e| {
+            tracing::error!("failed to unset setting `{key}`: {e}");
+            EX_IOERR
+        })?;
+        if !was_set {
+            ceprintln!("<s,y>warn:</> Setting <s>{key}</> is not set.");
+        }
+        return Ok(());
+    }
+
+    let Some(value) = value else {
+        let value = match module_name {
+            Some(module_name) => settings.effective(module_name, &key).await,
+            None => settings.get(None, &key).await,
+        }
+        .map_err(|e| {
+            tracing::error!("failed to read setting `{key}`: {e}");
+            EX_UNAVAILABLE
+        })?;
+        if let Some(value) = value {
+            println!("{value}");
+        }
+        return Ok(());
+    };
+
+    if !allowed.contains(&value.as_str()) {
+        ceprintln!(
+            "<s,r>error:</> invalid value `{value}` for setting `{key}`, expected one of: {}",
+            allowed.join(", ")
+        );
+        return Err(EX_USAGE);
+    }
+
+    settings.set(module_name, &key, &value).await.map_err(|e| {
+        tracing::error!("failed to write setting `{key}`: {e}");
+        EX_IOERR
+    })?;
+
+    if flags.verbose > 0 {
+        cprintln!("<s,g>✓</> Set <s>{key}</> to <s>{value}</>.");
+    }
+
+    Ok(())
+}
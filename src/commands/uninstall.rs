@@ -3,20 +3,90 @@
 use crate::{
     StandardOptions,
     SysexitsError::{self, *},
+    cache::Cache,
+    dependencies::Dependencies,
+    format_bytes,
+    installer::Versions,
+    models::{ModelFile, module_models},
+    pins::Pins,
+    reasons::InstallReasons,
+    secrets::SecretVariables,
+    selectors::Selection,
+    settings::Settings,
 };
-use color_print::cprintln;
+use asimov_env::paths::asimov_root;
+use color_print::{ceprintln, cprintln};
+use std::io;
 
 #[tokio::main]
 pub async fn uninstall(
-    module_names: Vec<String>,
+    selection: Selection,
+    purge: bool,
+    cascade: bool,
+    force: bool,
     flags: &StandardOptions,
 ) -> Result<(), SysexitsError> {
-    let installer = asimov_installer::Installer::default();
-    for module_name in module_names {
+    let registry = asimov_registry::Registry::default();
+    let installer = crate::installer::Installer::default();
+
+    let deps = Dependencies::installed(&registry).await.map_err(|e| {
+        tracing::error!("failed to read installed modules: {e}");
+        EX_UNAVAILABLE
+    })?;
+
+    let mut module_names = selection.resolve_installed(&registry).await?;
+    let dependents = deps.all_dependents(&module_names);
+    if !dependents.is_empty() {
+        if cascade {
+            cprintln!(
+                "<s,y>-</> Also uninstalling the dependent modules: <s>{}</>.",
+                dependents.join(", ")
+            );
+            module_names.extend(dependents);
+        } else if force {
+            ceprintln!(
+                "<s,y>warn:</> Uninstalling modules which are required by: <s>{}</>.",
+                dependents.join(", ")
+            );
+        } else {
+            for module_name in &module_names {
+                let dependents: Vec<_> = deps
+                    .dependents(module_name)
+                    .into_iter()
+                    .filter(|dependent| !module_names.contains(dependent))
+                    .collect();
+                if !dependents.is_empty() {
+                    ceprintln!(
+                        "<s,r>error:</> Module <s>{module_name}</> is required by: <s>{}</>.",
+                        dependents.join(", ")
+                    );
+                }
+            }
+            ceprintln!(
+                "<s,dim>hint:</> Uninstall the dependent modules too with: <s>asimov module uninstall --cascade</>, or regardless with: <s>--force</>"
+            );
+            return Err(EX_UNAVAILABLE);
+        }
+    }
+
+    for module_name in deps.removal_order(&module_names) {
         if flags.verbose > 1 {
             cprintln!("<s,c>»</> Uninstalling the module <s>{module_name}</>...");
         }
 
+        // The manifest is gone after uninstalling, so read the models first:
+        let models = if purge {
+            match registry.read_manifest(&module_name).await {
+                Ok(manifest) => module_models(&manifest.manifest),
+                Err(e) => {
+                    tracing::debug!(module_name, ?e, "unable to read manifest for purging");
+                    Vec::new()
+                },
+            }
+        } else {
+            Vec::new()
+        };
+
         installer
             .uninstall_module(&module_name)
             .await
@@ -25,9 +95,138 @@ pub async fn uninstall(
                 EX_UNAVAILABLE
             })?;
 
+        InstallReasons::default()
+            .remove(&module_name)
+            .await
+            .inspect_err(|e| tracing::warn!("failed to remove install reason: {e}"))
+            .ok();
+
         if flags.verbose > 0 {
             cprintln!("<s,g>✓</> Uninstalled the module <s>{module_name}</>.");
         }
+
+        if purge {
+            purge_module(&registry, &module_name, &models)
+                .await
+                .map_err(|e| {
+                    tracing::error!("failed to purge module `{module_name}`: {e}");
+                    EX_IOERR
+                })?;
+        }
     }
     Ok(())
 }
+
+/// Removes everything the module left behind besides its installed files,
+/// printing each removed item.
+async fn purge_module(
+    registry: &asimov_registry::Registry,
+    module_name: &str,
+    models: &[ModelFile],
+) -> io::Result<()> {
+    // Configuration of every profile:
+    let configs_dir = asimov_root().join("configs");
+    for profile in list_dirs(&configs_dir).await? {
+        let path = configs_dir.join(&profile).join(module_name);
+        if tokio::fs::try_exists(&path).await? {
+            tokio::fs::remove_dir_all(&path).await?;
+            cprintln!(
+                "<s,g>✓</> Removed configuration for profile <s>{profile}</> at {}.",
+                path.display()
+            );
+        }
+    }
+
+    // Models which no other installed module requires:
+    let mut still_required = Vec::new();
+    if !models.is_empty() {
+        let installed = registry
+            .installed_modules()
+            .await
+            .map_err(io::Error::other)?;
+        for manifest in installed {
+            still_required.extend(module_models(&manifest.manifest));
+        }
+    }
+    for model in models {
+        let Some(path) = model.local_path() else {
+            continue;
+        };
+        if still_required.contains(model) {
+            ceprintln!(
+                "<s,y>warn:</> Keeping model <s>{}</>, which other installed modules require.",
+                model.filename
+            );
+            continue;
+        }
+        let freed = model.remove()?;
+        cprintln!(
+            "<s,g>✓</> Removed model <s>{}</> at {}, freeing {}.",
+            model.filename,
+            path.display(),
+            format_bytes(freed)
+        );
+    }
+
+    // Downloaded release artifacts:
+    let release_url = format!("/asimov-{module_name}-module/releases/download/");
+    let removed = Cache::default()
+        .remove_matching(|url| url.contains(&release_url))
+        .await
+        .map_err(io::Error::other)?;
+    for entry in removed {
+        let name = entry
+            .urls
+            .first()
+            .and_then(|url| url.rsplit('/').next())
+            .unwrap_or_default();
+        cprintln!(
+            "<s,g>✓</> Removed download <s>{name}</> at {}, freeing {}.",
+            entry.path.display(),
+            format_bytes(entry.size)
+        );
+    }
+
+    // Versions kept for rollback, the pin, the secret marks and the
+    // module's settings:
+    let versions = Versions::default();
+    for kept in versions.list(module_name).await? {
+        cprintln!(
+            "<s,g>✓</> Removed kept version <s>{}</> at {}.",
+            kept.version,
+            kept.path.display()
+        );
+    }
+    versions.remove_all(module_name).await?;
+
+    if Pins::default().unpin(module_name).await? {
+        cprintln!("<s,g>✓</> Removed the pin of module <s>{module_name}</>.");
+    }
+
+    if SecretVariables::default().remove(module_name).await? {
+        cprintln!("<s,g>✓</> Removed the secret variable marks of module <s>{module_name}</>.");
+    }
+
+    if let Some(path) = Settings::default().remove_module(module_name).await? {
+        cprintln!("<s,g>✓</> Removed settings at {}.", path.display());
+    }
+
+    Ok(())
+}
+
+async fn list_dirs(dir: &std::path::Path) -> io::Result<Vec<String>> {
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut names = Vec::new();
+    while let Some(entry) = read_dir.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
@@ -1,22 +1,63 @@
 // This is free and unencumbered software released into the public domain.
 
+use crate::installer::{InstallOptions, Installer};
 use crate::{
     StandardOptions,
     SysexitsError::{self, *},
+    pins::Pins,
+    policy::{Channel, UpgradePolicy, parse_version, select_version},
+    selectors::Selection,
+    settings::Settings,
 };
-use asimov_installer::InstallOptions;
-use color_print::{cprintln, cstr};
+use color_print::{ceprintln, cprintln};
+use futures::StreamExt;
+
+/// The number of latest-version lookups done at the same time.
+const LOOKUP_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct UpgradeOptions {
+    /// Upgrade to this version instead of the latest.
+    pub version: Option<String>,
+    pub model_size: Option<String>,
+    /// Only check for pending upgrades, failing if there are any.
+    pub check: bool,
+    /// Only print the planned upgrades.
+    pub dry_run: bool,
+    /// The number of previous versions to keep for rolling back.
+    pub keep_versions: usize,
+    /// Show the release notes of the upgrades, asking for confirmation.
+    pub changes: bool,
+    /// Upgrade pinned modules too.
+    pub ignore_pins: bool,
+    /// The number of modules upgraded at the same time.
+    pub jobs: usize,
+    /// Overrides the configured upgrade policy of the modules.
+    pub policy: Option<UpgradePolicy>,
+    /// Consider pre-releases, overriding the configured release channel.
+    pub pre: bool,
+}
+
+/// The result of upgrading a module, for the final report.
+enum Outcome {
+    Upgraded(String),
+    UpToDate(String),
+    Skipped(String),
+    Failed(String),
+}
 
 #[tokio::main]
 pub async fn upgrade(
-    module_names: Vec<String>,
-    version: Option<String>,
-    model_size: Option<String>,
+    selection: Selection,
+    options: UpgradeOptions,
     flags: &StandardOptions,
 ) -> Result<(), SysexitsError> {
     let registry = asimov_registry::Registry::default();
-    let installer = asimov_installer::Installer::default();
+    let installer = Installer::default()
+        .with_retention(options.keep_versions)
+        .with_pre(options.pre);
 
+    let module_names = selection.resolve_installed(&registry).await?;
     let module_names = if !module_names.is_empty() {
         module_names
     } else {
@@ -32,63 +73,436 @@ pub async fn upgrade(
             .collect()
     };
 
-    let install_options = InstallOptions::builder()
-        .maybe_version(version.clone())
-        .maybe_model_size(model_size)
-        .build();
+    // Look up the target versions of all modules concurrently:
+    let plans: Vec<(String, Plan)> = futures::stream::iter(module_names)
+        .map(|module_name| {
+            let (registry, installer, options) = (&registry, &installer, &options);
+            async move {
+                let plan = plan_upgrade(registry, installer, &module_name, options).await;
+                (module_name, plan)
+            }
+        })
+        .buffered(LOOKUP_CONCURRENCY)
+        .collect()
+        .await;
 
-    for module_name in module_names {
-        let current = registry.module_version(&module_name).await.map_err(|e| {
-            tracing::error!("failed to read installed version of `{module_name}`");
-            EX_UNAVAILABLE
-        })?;
+    let mut outcomes = Vec::new();
+    let mut upgrades = Vec::new();
+    let mut held_back = false;
+    for (module_name, plan) in plans {
+        let (current, target) = match plan {
+            Plan::Done(outcome) => {
+                outcomes.push((module_name, outcome));
+                continue;
+            },
+            Plan::HeldBack { current, latest } => {
+                held_back = true;
+                outcomes.push((
+                    module_name,
+                    Outcome::Skipped(format!(
+                        "held back at {current}, {latest} is a major upgrade"
+                    )),
+                ));
+                continue;
+            },
+            Plan::Upgrade {
+                current,
+                target,
+                newer,
+            } => {
+                if let Some(newer) = newer {
+                    held_back = true;
+                    cprintln!(
+                        "<s,y>-</> Module <s>{module_name}</> has a major upgrade to <s>{newer}</> available."
+                    );
+                }
+                (current, target)
+            },
+        };
+
+        if options.changes {
+            // Failing to fetch the notes is reported, but doesn't block:
+            let _ = super::changelog::print_changes(
+                &installer,
+                &module_name,
+                current.as_deref(),
+                Some(&target),
+            )
+            .await;
+        }
 
-        let target_version = if let Some(ref want) = version {
-            want.clone()
+        if options.check || options.dry_run {
+            cprintln!(
+                "<s>{module_name}</>: {} → <s>{target}</>",
+                current.as_deref().unwrap_or("unknown")
+            );
+            upgrades.push((module_name, current, target));
+            continue;
+        }
+
+        if options.changes
+            && crate::is_interactive()
+            && !crate::confirm(
+                &format!("Upgrade module `{module_name}` to version {target}?"),
+                true,
+            )?
+        {
+            outcomes.push((module_name, Outcome::Skipped("declined".into())));
+            continue;
+        }
+
+        upgrades.push((module_name, current, target));
+    }
+
+    if options.check || options.dry_run {
+        let failed = print_report(&mut outcomes, flags);
+        if held_back {
+            print_held_back_hint();
+        }
+        return if failed {
+            Err(EX_UNAVAILABLE)
+        } else if options.check && !upgrades.is_empty() {
+            Err(EX_TEMPFAIL)
         } else {
-            installer
-                .fetch_latest_release(&module_name)
-                .await
-                .map_err(|e| {
-                    tracing::error!(
-                        "unable to find latest release for module `{module_name}`: {e}"
-                    );
-                    EX_UNAVAILABLE
-                })?
+            Ok(())
         };
+    }
+
+    // Install missing dependencies beforehand, once each, as upgrades done
+    // at the same time could otherwise both install the same dependency:
+    let upgrades = install_dependencies(
+        &registry,
+        &installer,
+        upgrades,
+        &options,
+        &mut outcomes,
+        flags,
+    )
+    .await;
 
-        if current.is_some_and(|current| current == target_version) {
-            if flags.verbose > 0 {
-                let vers_txt = if version.is_some() {
-                    "version"
-                } else {
-                    "latest version"
+    // Then upgrade the modules, a few at a time:
+    let upgraded: Vec<(String, Outcome)> = futures::stream::iter(upgrades)
+        .map(|(module_name, current, target)| {
+            let (installer, options) = (&installer, &options);
+            async move {
+                if flags.verbose > 1 {
+                    cprintln!("<s,c>»</> Upgrading module <s>{module_name}</>...");
+                }
+
+                let install_options = InstallOptions::builder()
+                    .version(target.clone())
+                    .maybe_model_size(options.model_size.clone())
+                    .build();
+
+                let outcome = match installer
+                    .upgrade_module(&module_name, &install_options)
+                    .await
+                {
+                    Ok(()) => Outcome::Upgraded(format!(
+                        "{} → {target}",
+                        current.as_deref().unwrap_or("unknown")
+                    )),
+                    Err(e) => {
+                        tracing::debug!(module_name, ?e, "module upgrade failed");
+                        Outcome::Failed(e.to_string())
+                    },
                 };
+                (module_name, outcome)
+            }
+        })
+        .buffer_unordered(options.jobs.max(1))
+        .collect()
+        .await;
+    outcomes.extend(upgraded);
 
-                cprintln!(
-                    "<s,g>✓</> Module <s>{module_name}</> already has {vers_txt} <s>{target_version}</> installed."
-                );
+    let failed = print_report(&mut outcomes, flags);
+    if held_back {
+        print_held_back_hint();
+    }
+
+    if failed {
+        return Err(EX_UNAVAILABLE);
+    }
+
+    Ok(())
+}
+
+/// Installs the dependencies of the target versions which aren't installed,
+/// returning the upgrades whose dependencies could all be installed. The
+/// others are reported as failed.
+async fn install_dependencies(
+    registry: &asimov_registry::Registry,
+    installer: &Installer,
+    upgrades: Vec<(String, Option<String>, String)>,
+    options: &UpgradeOptions,
+    outcomes: &mut Vec<(String, Outcome)>,
+    flags: &StandardOptions,
+) -> Vec<(String, Option<String>, String)> {
+    let manifests: Vec<_> = futures::stream::iter(&upgrades)
+        .map(|(module_name, _, target)| installer.fetch_manifest(module_name, target))
+        .buffered(LOOKUP_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut ready = Vec::new();
+    let mut failed = std::collections::BTreeMap::new();
+    for ((module_name, current, target), manifest) in upgrades.into_iter().zip(manifests) {
+        let manifest = match manifest {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                outcomes.push((
+                    module_name,
+                    Outcome::Failed(format!("failed to fetch module manifest: {e}")),
+                ));
+                continue;
+            },
+        };
+
+        let mut failure = None;
+        for dependency in manifest.requires.map(|r| r.modules).unwrap_or_default() {
+            if let Some(reason) = failed.get(&dependency) {
+                failure = Some(String::clone(reason));
+                break;
+            }
+            if registry
+                .is_module_installed(&dependency)
+                .await
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            if flags.verbose > 1 {
+                cprintln!("<s,c>»</> Installing dependency module <s>{dependency}</>...");
+            }
+            if let Err(e) = installer
+                .install_dependency(&dependency, options.model_size.as_deref())
+                .await
+            {
+                tracing::debug!(dependency, ?e, "dependency install failed");
+                failure = Some(e.to_string());
+                failed.insert(dependency, e.to_string());
+                break;
             }
-            continue;
         }
 
-        if flags.verbose > 1 {
-            cprintln!("<s,c>»</> Upgrading module <s>{module_name}</>...");
+        match failure {
+            Some(reason) => outcomes.push((module_name, Outcome::Failed(reason))),
+            None => ready.push((module_name, current, target)),
         }
+    }
+    ready
+}
 
-        installer
-            .upgrade_module(module_name.clone(), &install_options)
+enum Plan {
+    /// Nothing to do, the outcome is already known.
+    Done(Outcome),
+    /// The module is to be upgraded to `target`, with a `newer` release
+    /// which isn't allowed by the upgrade policy.
+    Upgrade {
+        current: Option<String>,
+        target: String,
+        newer: Option<String>,
+    },
+    /// The only newer release isn't allowed by the upgrade policy.
+    HeldBack { current: String, latest: String },
+}
+
+/// Determines what to do with the module, never moving to an older version
+/// than is installed unless the versions can't be compared.
+async fn plan_upgrade(
+    registry: &asimov_registry::Registry,
+    installer: &Installer,
+    module_name: &str,
+    options: &UpgradeOptions,
+) -> Plan {
+    match try_plan_upgrade(registry, installer, module_name, options).await {
+        Ok(plan) => plan,
+        Err(reason) => Plan::Done(Outcome::Failed(reason)),
+    }
+}
+
+async fn try_plan_upgrade(
+    registry: &asimov_registry::Registry,
+    installer: &Installer,
+    module_name: &str,
+    options: &UpgradeOptions,
+) -> Result<Plan, String> {
+    if !options.ignore_pins {
+        let pin = Pins::default()
+            .get(module_name)
             .await
-            .map_err(|e| {
-                tracing::error!("module upgrade failed for `{module_name}`: {e}");
-                EX_UNAVAILABLE
-            })?;
+            .map_err(|e| format!("failed to read pin: {e}"))?;
+        if let Some(pin) = pin {
+            return Ok(Plan::Done(Outcome::Skipped(match pin.version {
+                Some(version) => format!("pinned at {version}"),
+                None => "pinned".into(),
+            })));
+        }
+    }
 
-        if flags.verbose > 0 {
-            cprintln!(
-                "<s,g>✓</> Upgraded module <s>{module_name}</> to version <s>{target_version}</>."
-            );
+    let current = registry
+        .module_version(module_name)
+        .await
+        .map_err(|e| format!("failed to read installed version: {e}"))?;
+    let current_semver = current.as_deref().and_then(parse_version);
+
+    if let Some(ref want) = options.version {
+        if let (Some(current), Some(current_semver), Some(want_semver)) =
+            (&current, &current_semver, parse_version(want))
+            && want_semver < *current_semver
+        {
+            return Err(format!(
+                "version {want} is older than the installed version {current}, \
+                 downgrade with `asimov module reinstall {module_name} --version {want}`"
+            ));
         }
+        return Ok(if current.as_ref() == Some(want) {
+            Plan::Done(Outcome::UpToDate(want.clone()))
+        } else {
+            Plan::Upgrade {
+                current,
+                target: want.clone(),
+                newer: None,
+            }
+        });
     }
-    Ok(())
+
+    let channel = if options.pre {
+        Channel::Dev
+    } else {
+        Settings::default()
+            .channel(module_name)
+            .await
+            .map_err(|e| format!("failed to read release channel: {e}"))?
+    };
+
+    let latest = installer
+        .fetch_latest_on_channel(module_name, channel)
+        .await
+        .map_err(|e| format!("unable to find latest release: {e}"))?;
+
+    let (Some(current), Some(current_semver), Some(latest_semver)) =
+        (&current, current_semver, parse_version(&latest))
+    else {
+        tracing::debug!(module_name, ?current, latest, "unable to compare versions");
+        return Ok(if current.as_ref() == Some(&latest) {
+            Plan::Done(Outcome::UpToDate(latest))
+        } else {
+            Plan::Upgrade {
+                current,
+                target: latest,
+                newer: None,
+            }
+        });
+    };
+
+    if latest_semver <= current_semver {
+        return Ok(Plan::Done(Outcome::UpToDate(current.clone())));
+    }
+
+    let policy = match options.policy {
+        Some(policy) => policy,
+        None => module_policy(module_name).await?,
+    };
+
+    if policy.allows(&current_semver, &latest_semver) {
+        return Ok(Plan::Upgrade {
+            current: Some(current.clone()),
+            target: latest,
+            newer: None,
+        });
+    }
+
+    let releases = installer
+        .fetch_releases(module_name)
+        .await
+        .map_err(|e| format!("unable to list releases: {e}"))?;
+
+    Ok(
+        match select_version(
+            &current_semver,
+            releases.iter().map(|release| release.version.as_str()),
+            policy,
+            channel.includes_pre(),
+        ) {
+            Some(version) => Plan::Upgrade {
+                current: Some(current.clone()),
+                target: version.into(),
+                newer: Some(latest),
+            },
+            None => Plan::HeldBack {
+                current: current.clone(),
+                latest,
+            },
+        },
+    )
+}
+
+/// Returns the upgrade policy configured for the module, or the default.
+async fn module_policy(module_name: &str) -> Result<UpgradePolicy, String> {
+    let value = Settings::default()
+        .effective(module_name, "upgrade-policy")
+        .await
+        .map_err(|e| format!("failed to read upgrade policy: {e}"))?;
+
+    match value.as_deref().map(str::parse) {
+        None => Ok(UpgradePolicy::default()),
+        Some(Ok(policy)) => Ok(policy),
+        Some(Err(e)) => {
+            ceprintln!("<s,y>warn:</> Ignoring the setting for module <s>{module_name}</>: {e}.");
+            Ok(UpgradePolicy::default())
+        },
+    }
+}
+
+/// Prints the table of upgraded, skipped and failed modules, returning
+/// whether any module failed. Up-to-date modules are only listed when
+/// verbose.
+fn print_report(outcomes: &mut [(String, Outcome)], flags: &StandardOptions) -> bool {
+    outcomes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let rows: Vec<_> = outcomes
+        .iter()
+        .filter(|(_, outcome)| flags.verbose > 0 || !matches!(outcome, Outcome::UpToDate(_)))
+        .collect();
+
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (module_name, outcome) in rows {
+        match outcome {
+            Outcome::Upgraded(details) => {
+                cprintln!(
+                    "<s>{module_name:<width$}</>  <s,g>{:<9}</> {details}",
+                    "upgraded"
+                )
+            },
+            Outcome::UpToDate(version) => {
+                cprintln!(
+                    "<s>{module_name:<width$}</>  <s,g>{:<9}</> at {version}",
+                    "current"
+                )
+            },
+            Outcome::Skipped(reason) => {
+                cprintln!(
+                    "<s>{module_name:<width$}</>  <s,y>{:<9}</> {reason}",
+                    "skipped"
+                )
+            },
+            Outcome::Failed(reason) => {
+                cprintln!(
+                    "<s>{module_name:<width$}</>  <s,r>{:<9}</> {reason}",
+                    "failed"
+                )
+            },
+        }
+    }
+
+    outcomes
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, Outcome::Failed(_)))
+}
+
+fn print_held_back_hint() {
+    ceprintln!(
+        "<s,dim>hint:</> Allow major upgrades with: <s>asimov module upgrade --major</>, or for a module with: <s>asimov module settings --module <<name>> upgrade-policy major</>"
+    );
 }
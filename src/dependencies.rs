@@ -0,0 +1,137 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Dependencies between installed modules, as declared in their manifests
+//! under `requires.modules`.
+
+use asimov_module::InstalledModuleManifest;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, Default)]
+pub struct Dependencies {
+    /// The modules required by each installed module.
+    requires: BTreeMap<String, Vec<String>>,
+}
+
+impl Dependencies {
+    pub fn from_manifests<'a>(
+        manifests: impl IntoIterator<Item = &'a InstalledModuleManifest>,
+    ) -> Self {
+        let requires = manifests
+            .into_iter()
+            .map(|manifest| {
+                let modules = manifest
+                    .manifest
+                    .requires
+                    .as_ref()
+                    .map(|requires| requires.modules.clone())
+                    .unwrap_or_default();
+                (manifest.manifest.name.clone(), modules)
+            })
+            .collect();
+        Self { requires }
+    }
+
+    /// Reads the dependencies between the installed modules.
+    pub async fn installed(
+        registry: &asimov_registry::Registry,
+    ) -> Result<Self, asimov_registry::error::InstalledModulesError> {
+        let manifests = registry.installed_modules().await?;
+        Ok(Self::from_manifests(&manifests))
+    }
+
+    /// Returns the modules that the module requires directly.
+    pub fn requirements(&self, module_name: &str) -> &[String] {
+        self.requires
+            .get(module_name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the installed modules that require the module directly.
+    pub fn dependents(&self, module_name: &str) -> Vec<String> {
+        self.requires
+            .iter()
+            .filter(|(_, modules)| modules.iter().any(|m| m == module_name))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Returns the installed modules that require any of the modules,
+    /// directly or indirectly, excluding the modules themselves.
+    pub fn all_dependents(&self, module_names: &[String]) -> Vec<String> {
+        let mut found = BTreeSet::new();
+        let mut queue: Vec<String> = module_names.to_vec();
+        while let Some(module_name) = queue.pop() {
+            for dependent in self.dependents(&module_name) {
+                if !module_names.contains(&dependent) && found.insert(dependent.clone()) {
+                    queue.push(dependent);
+                }
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    /// Orders the modules so that each comes before the modules it requires,
+    /// which is the order to remove them in.
+    pub fn removal_order(&self, module_names: &[String]) -> Vec<String> {
+        fn visit(
+            deps: &Dependencies,
+            module_name: &str,
+            module_names: &[String],
+            visited: &mut BTreeSet<String>,
+            order: &mut Vec<String>,
+        ) {
+            if !visited.insert(module_name.into()) {
+                return;
+            }
+            for dependent in deps.dependents(module_name) {
+                if module_names.contains(&dependent) {
+                    visit(deps, &dependent, module_names, visited, order);
+                }
+            }
+            order.push(module_name.into());
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut order = Vec::new();
+        for module_name in module_names {
+            visit(self, module_name, module_names, &mut visited, &mut order);
+        }
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(name: &str, requires: &[&str]) -> InstalledModuleManifest {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "label": "",
+            "summary": "",
+            "links": [],
+            "requires": { "modules": requires },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dependents() {
+        let manifests = [
+            manifest("a", &[]),
+            manifest("b", &["a"]),
+            manifest("c", &["b"]),
+            manifest("d", &[]),
+        ];
+        let deps = Dependencies::from_manifests(&manifests);
+
+        assert_eq!(deps.requirements("b"), ["a"]);
+        assert_eq!(deps.dependents("a"), ["b"]);
+        assert_eq!(deps.all_dependents(&["a".into()]), ["b", "c"]);
+        assert!(deps.all_dependents(&["d".into()]).is_empty());
+
+        let names = ["a".into(), "b".into(), "c".into()];
+        assert_eq!(deps.removal_order(&names), ["c", "b", "a"]);
+    }
+}
@@ -0,0 +1,554 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Installs modules from their prebuilt GitHub releases, downloading release
+//! assets through the local download [`Cache`].
+//!
+//! Resolving the latest release and uninstalling are delegated to the
+//! `asimov-installer` crate, whose error types are shared here. The crate
+//! doesn't expose its download step, so the fetching of manifests, checksums
+//! and release assets is done here in order to go through the cache.
+
+pub mod error;
+mod github;
+pub mod platform;
+pub mod self_update;
+pub mod versions;
+
+pub use asimov_installer::InstallOptions;
+pub use platform::PlatformInfo;
+pub use versions::{KeptVersion, Versions};
+
+use crate::{
+    cache::Cache,
+    policy::{Channel, newest_version},
+    reasons::{InstallReason, InstallReasons},
+    secrets::{SecretVariables, manifest_secrets},
+    settings::Settings,
+};
+use asimov_module::{InstalledModuleManifest, ModuleManifest, RequiredModel};
+use asimov_registry::{Registry, error::RemoveManifestError};
+use error::*;
+use std::path::Path;
+
+/// A published release of a module.
+#[derive(Clone, Debug)]
+pub struct Release {
+    pub version: String,
+    /// The release notes, in Markdown.
+    pub notes: Option<String>,
+    /// The publication timestamp, in RFC 3339 format.
+    pub published_at: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Installer {
+    client: reqwest::Client,
+    registry: Registry,
+    cache: Cache,
+    versions: Versions,
+    retention: usize,
+    pre: bool,
+    inner: asimov_installer::Installer,
+}
+
+impl Default for Installer {
+    fn default() -> Self {
+        // No overall timeout, as release assets may be large:
+        let client = reqwest::Client::builder()
+            .user_agent("asimov-module-cli")
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .read_timeout(std::time::Duration::from_secs(30))
+            .build()
+            .expect("Failed to build HTTP client");
+        Self::new(client, Registry::default(), Cache::default())
+    }
+}
+
+impl Installer {
+    pub fn new(client: reqwest::Client, registry: Registry, cache: Cache) -> Self {
+        let inner = asimov_installer::Installer::new(client.clone(), registry.clone());
+        Self {
+            client,
+            registry,
+            cache,
+            versions: Versions::default(),
+            retention: versions::DEFAULT_RETENTION,
+            pre: false,
+            inner,
+        }
+    }
+
+    /// Sets how many previous versions of each module to keep on upgrade,
+    /// for rolling back. Zero disables keeping previous versions.
+    pub fn with_retention(mut self, retention: usize) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Sets whether to consider pre-releases when no version is requested,
+    /// for the installed module and its dependencies, overriding their
+    /// configured release channels.
+    pub fn with_pre(mut self, pre: bool) -> Self {
+        self.pre = pre;
+        self
+    }
+
+    pub fn versions(&self) -> &Versions {
+        &self.versions
+    }
+
+    pub async fn fetch_latest_release(
+        &self,
+        module_name: impl AsRef<str>,
+    ) -> Result<String, FetchError> {
+        self.inner.fetch_latest_release(module_name).await
+    }
+
+    /// Fetches the manifest of the module as published at the version.
+    pub async fn fetch_manifest(
+        &self,
+        module_name: &str,
+        version: &str,
+    ) -> Result<ModuleManifest, FetchManifestError> {
+        github::fetch_module_manifest(&self.client, module_name, version).await
+    }
+
+    /// Fetches the latest release of the module on the channel.
+    pub async fn fetch_latest_on_channel(
+        &self,
+        module_name: &str,
+        channel: Channel,
+    ) -> Result<String, LatestReleaseError> {
+        match channel {
+            Channel::Stable => Ok(self.fetch_latest_release(module_name).await?),
+            Channel::Dev => {
+                let releases = self.fetch_releases(module_name).await?;
+                newest_version(releases.iter().map(|r| r.version.as_str()), channel)
+                    .map(String::from)
+                    .ok_or(LatestReleaseError::NoReleases(channel))
+            },
+        }
+    }
+
+    /// Fetches the latest release of the module on the channel it follows,
+    /// which is `dev` if pre-releases were requested, and otherwise its
+    /// configured channel.
+    pub async fn fetch_latest_for(&self, module_name: &str) -> Result<String, LatestReleaseError> {
+        let channel = if self.pre {
+            Channel::Dev
+        } else {
+            Settings::default()
+                .channel(module_name)
+                .await
+                .map_err(LatestReleaseError::Channel)?
+        };
+        self.fetch_latest_on_channel(module_name, channel).await
+    }
+
+    /// Fetches the most recent releases of the module, newest first.
+    pub async fn fetch_releases(&self, module_name: &str) -> Result<Vec<Release>, FetchError> {
+        let releases =
+            github::fetch_releases(&self.client, &github::module_repo(module_name)).await?;
+        Ok(releases
+            .into_iter()
+            .filter(|release| !release.tag_name.is_empty())
+            .map(|release| Release {
+                version: release.tag_name,
+                notes: release.body.filter(|body| !body.trim().is_empty()),
+                published_at: release.published_at,
+            })
+            .collect())
+    }
+
+    pub async fn install_module(
+        &self,
+        module_name: &str,
+        options: &InstallOptions,
+    ) -> Result<(), InstallError> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("asimov-module-installer")
+            .tempdir()
+            .map_err(InstallError::CreateTempDir)?;
+
+        let (manifest, version, secrets) = self
+            .preinstall(module_name, options, temp_dir.path())
+            .await?;
+
+        self.finish_install(&version, manifest, &secrets, temp_dir.path())
+            .await
+    }
+
+    pub async fn upgrade_module(
+        &self,
+        module_name: &str,
+        options: &InstallOptions,
+    ) -> Result<(), UpgradeError> {
+        let version = if let Some(ref want_version) = options.version {
+            want_version.clone()
+        } else {
+            self.fetch_latest_for(module_name).await?
+        };
+
+        let current_version = self.registry.module_version(module_name).await?;
+        match current_version {
+            Some(current) if current == version => return Ok(()),
+            Some(_) => (),
+            None => tracing::debug!(module_name, "installed module does not define a version"),
+        };
+
+        if self.retention > 0 {
+            match self.registry.read_manifest(module_name).await {
+                Ok(current) => {
+                    self.versions
+                        .save(&current)
+                        .await
+                        .map_err(UpgradeError::KeepVersion)?;
+                },
+                Err(err) => tracing::debug!(
+                    module_name,
+                    ?err,
+                    "unable to read installed manifest, not keeping current version"
+                ),
+            }
+        }
+
+        let options = InstallOptions {
+            version: Some(version),
+            ..options.clone()
+        };
+        self.reinstall_module(module_name, &options).await?;
+
+        self.versions
+            .prune(module_name, self.retention)
+            .await
+            .map_err(UpgradeError::KeepVersion)?;
+
+        Ok(())
+    }
+
+    /// Restores a previously kept version of the module, by default the most
+    /// recently kept one, returning the restored version. The current version
+    /// is kept in turn, so that the rollback itself can be undone.
+    pub async fn rollback_module(
+        &self,
+        module_name: &str,
+        version: Option<&str>,
+    ) -> Result<String, RollbackError> {
+        let current = self.registry.read_manifest(module_name).await?;
+        let current_version = current.version.as_deref();
+
+        let kept = self
+            .versions
+            .list(module_name)
+            .await?
+            .into_iter()
+            .filter(|kept| Some(kept.version.as_str()) != current_version)
+            .find(|kept| version.is_none_or(|version| kept.version == version))
+            .ok_or_else(|| match version {
+                Some(version) => RollbackError::NoSuchVersion(version.into()),
+                None => RollbackError::NothingKept,
+            })?;
+
+        let was_enabled = self.registry.is_module_enabled(module_name).await?;
+
+        self.versions.save(&current).await?;
+        let restored = self.versions.restore(&kept, Some(&current)).await?;
+        // Keep at least the version just saved, for undoing the rollback:
+        self.versions
+            .prune(module_name, self.retention.max(1))
+            .await?;
+
+        // Refresh the link, in case the manifest was previously in YAML:
+        if was_enabled {
+            self.registry.disable_module(module_name).await?;
+            self.registry.enable_module(module_name).await?;
+        }
+
+        Ok(restored.version.unwrap_or(kept.version))
+    }
+
+    /// Fetches and lays down the module again, replacing the currently
+    /// installed files. The module's configuration is left untouched, and
+    /// the module is re-enabled if it was enabled before.
+    ///
+    /// This also works when the installed manifest is unreadable, in which
+    /// case the programs listed in the newly fetched manifest are replaced.
+    pub async fn reinstall_module(
+        &self,
+        module_name: &str,
+        options: &InstallOptions,
+    ) -> Result<(), ReinstallError> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("asimov-module-installer")
+            .tempdir()
+            .map_err(InstallError::CreateTempDir)?;
+
+        // check if currently enabled, have to re-enable after reinstall
+        let was_enabled = self.registry.is_module_enabled(module_name).await?;
+
+        let (manifest, version, secrets) = self
+            .preinstall(module_name, options, temp_dir.path())
+            .await?;
+
+        // now ok to uninstall old version
+        if let Err(err) = self.uninstall_module(module_name).await {
+            tracing::debug!(
+                module_name,
+                ?err,
+                "uninstall failed, removing files directly"
+            );
+            self.remove_module_files(module_name, &manifest).await?;
+        }
+
+        self.finish_install(&version, manifest, &secrets, temp_dir.path())
+            .await?;
+
+        if was_enabled {
+            self.registry.enable_module(module_name).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn uninstall_module(
+        &self,
+        module_name: impl AsRef<str>,
+    ) -> Result<(), UninstallError> {
+        self.inner.uninstall_module(module_name).await
+    }
+
+    /// Removes the files of a module whose installed manifest can't be read,
+    /// based on the programs listed in `manifest`.
+    async fn remove_module_files(
+        &self,
+        module_name: &str,
+        manifest: &ModuleManifest,
+    ) -> Result<(), ReinstallError> {
+        self.registry.disable_module(module_name).await?;
+
+        for program in &manifest.provides.programs {
+            self.registry
+                .remove_binary(program)
+                .await
+                .map_err(|e| ReinstallError::RemoveBinary(program.into(), e))?;
+        }
+
+        match self.registry.remove_manifest(module_name).await {
+            Ok(()) | Err(RemoveManifestError::NotInstalled) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Downloads and extracts the module, along with its dependencies and
+    /// models, returning its manifest, version, and the variables the
+    /// manifest marks as secret.
+    async fn preinstall(
+        &self,
+        module_name: &str,
+        options: &InstallOptions,
+        temp_dir: &Path,
+    ) -> Result<(ModuleManifest, String, Vec<String>), InstallError> {
+        let platform = platform::detect_platform();
+
+        let version = if let Some(ref want_version) = options.version {
+            want_version.clone()
+        } else {
+            self.fetch_latest_for(module_name).await?
+        };
+
+        let manifest_source =
+            github::fetch_module_manifest_source(&self.client, module_name, &version)
+                .await
+                .map_err(FetchManifestError::from)?;
+        let manifest: ModuleManifest =
+            serde_yml::from_str(&manifest_source).map_err(FetchManifestError::from)?;
+
+        let (filename, asset_path) = self
+            .download_matching_asset(module_name, &version, &platform)
+            .await?;
+
+        if let Some(subdeps) = manifest.requires.as_ref().map(|r| r.modules.clone()) {
+            for module in subdeps {
+                Box::pin(self.install_dependency(&module, options.model_size.as_deref())).await?;
+            }
+        };
+
+        let extract_dir = temp_dir.join("extract");
+        tokio::fs::create_dir(&extract_dir)
+            .await
+            .map_err(InstallError::CreateExtractDir)?;
+
+        github::extract_files(&asset_path, &filename, &extract_dir)
+            .await
+            .map_err(InstallError::Extract)?;
+
+        if let Some(ref requires) = manifest.requires {
+            for (name, model) in &requires.models {
+                let Some(repo) = name.strip_prefix("hf:") else {
+                    tracing::debug!(
+                        ?name,
+                        "unexpected format for required model, only `hf:<user>/<repo>` is supported"
+                    );
+                    continue;
+                };
+
+                let filename = match (model, &options.model_size) {
+                    (RequiredModel::Url(url), None | Some(_)) => url,
+                    (RequiredModel::Choices(choices), None) => {
+                        if choices
+                            .iter()
+                            .any(|(_, url)| asimov_huggingface::file_exists(repo, url).is_some())
+                        {
+                            // user didn't specify a model size/version to install
+                            // and one of the choices is already installed
+                            continue;
+                        }
+                        let Some((_, model)) = choices.first() else {
+                            // malformed manifest?
+                            tracing::warn!(
+                                ?module_name,
+                                "manifest defines required models with no choices"
+                            );
+                            continue;
+                        };
+                        model
+                    },
+                    (RequiredModel::Choices(choices), Some(want_model)) => {
+                        &choices
+                            .iter()
+                            .find(|(name, _)| *name == *want_model)
+                            .ok_or_else(|| InstallError::NoSuchModel(want_model.clone()))?
+                            .1
+                    },
+                };
+
+                // Models are kept in the Hugging Face cache, which already
+                // deduplicates and resumes interrupted downloads:
+                let (repo, filename) = (repo.to_string(), filename.clone());
+                tokio::task::spawn_blocking(move || {
+                    asimov_huggingface::ensure_file(&repo, &filename)
+                })
+                .await
+                .map_err(InstallError::ModelTask)??;
+            }
+        }
+
+        let secrets = manifest_secrets(&manifest_source);
+        Ok((manifest, version, secrets))
+    }
+
+    /// Installs the dependency module, unless already installed, recording
+    /// that it was installed automatically.
+    pub async fn install_dependency(
+        &self,
+        module_name: &str,
+        model_size: Option<&str>,
+    ) -> Result<(), InstallError> {
+        if self
+            .registry
+            .is_module_installed(module_name)
+            .await
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+
+        // pass the model_size option to dependencies
+        let options = InstallOptions::builder()
+            .maybe_model_size(model_size.map(String::from))
+            .build();
+        self.install_module(module_name, &options)
+            .await
+            .map_err(|e| InstallError::Dependency(module_name.into(), Box::new(e)))?;
+        InstallReasons::default()
+            .set(module_name, InstallReason::Auto)
+            .await
+            .map_err(|e| InstallError::MarkDependency(module_name.into(), e))
+    }
+
+    /// Fetches the release asset matching the platform, returning its file
+    /// name and its path in the download cache.
+    pub async fn download_matching_asset(
+        &self,
+        module_name: &str,
+        version: &str,
+        platform: &PlatformInfo,
+    ) -> Result<(String, std::path::PathBuf), InstallError> {
+        self.download_asset(&github::module_repo(module_name), version, platform)
+            .await
+    }
+
+    /// Fetches the release asset of the GitHub repository matching the
+    /// platform, returning its file name and its path in the download cache.
+    async fn download_asset(
+        &self,
+        repo: &str,
+        version: &str,
+        platform: &PlatformInfo,
+    ) -> Result<(String, std::path::PathBuf), InstallError> {
+        for filename in github::asset_filenames(repo, platform) {
+            let url = github::asset_url(repo, version, &filename);
+
+            tracing::debug!("trying asset URL {url}...");
+
+            let checksum = github::fetch_checksum(&self.client, &url)
+                .await
+                .map_err(InstallError::FetchChecksum)?;
+
+            if let Some(path) = self
+                .cache
+                .fetch(&self.client, &url, checksum.as_deref())
+                .await?
+            {
+                return Ok((filename, path));
+            }
+        }
+
+        let available = github::fetch_release(&self.client, repo, version)
+            .await
+            .inspect_err(|err| tracing::debug!(?err, "failed to list release assets"))
+            .map(|release| github::asset_targets(repo, &release))
+            .unwrap_or_default();
+
+        Err(InstallError::NotAvailable {
+            platform: platform.to_string(),
+            available,
+        })
+    }
+
+    async fn finish_install(
+        &self,
+        version: &str,
+        manifest: ModuleManifest,
+        secrets: &[String],
+        temp_dir: &Path,
+    ) -> Result<(), InstallError> {
+        let extract_dir = temp_dir.join("extract");
+
+        for program in &manifest.provides.programs {
+            let src = extract_dir.join(program);
+
+            // On Windows add the .exe extension to the binary name:
+            #[cfg(windows)]
+            let src = src.with_extension("exe");
+
+            self.registry.add_binary(program, &src).await?;
+        }
+
+        // Remember which variables the manifest marks as secret, to store
+        // their values encrypted:
+        SecretVariables::default()
+            .add(&manifest.name, secrets)
+            .await
+            .map_err(InstallError::MarkSecrets)?;
+
+        let installed_manifest = InstalledModuleManifest {
+            version: Some(version.into()),
+            manifest,
+        };
+
+        self.registry.add_manifest(installed_manifest).await?;
+
+        Ok(())
+    }
+}
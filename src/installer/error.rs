@@ -0,0 +1,136 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::cache::CacheError;
+use asimov_registry::error as registry;
+use std::io;
+use thiserror::Error;
+
+pub use asimov_installer::error::{
+    DeserializeError, FetchChecksumError, FetchError, HttpError, UninstallError,
+};
+
+#[derive(Debug, Error)]
+pub enum FetchManifestError {
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error("YAML deserialization failed: {0}")]
+    Yaml(#[from] serde_yml::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum LatestReleaseError {
+    #[error("failed to read release channel: {0}")]
+    Channel(io::Error),
+    #[error("failed to fetch releases: {0}")]
+    Fetch(#[from] FetchError),
+    #[error("no releases found on the `{0}` channel")]
+    NoReleases(crate::policy::Channel),
+}
+
+#[derive(Debug, Error)]
+pub enum InstallError {
+    #[error("failed to create directory for downloading: {0}")]
+    CreateTempDir(io::Error),
+    #[error("failed to fetch release: {0}")]
+    FetchRelease(#[from] LatestReleaseError),
+    #[error("failed to fetch module manifest: {0}")]
+    FetchManifest(#[from] FetchManifestError),
+    #[error("failed to fetch checksum: {0}")]
+    FetchChecksum(FetchChecksumError),
+    #[error(
+        "no binaries available for platform `{platform}`{}",
+        if available.is_empty() { String::new() } else { format!(", available targets: {}", available.join(", ")) }
+    )]
+    NotAvailable {
+        platform: String,
+        available: Vec<String>,
+    },
+    #[error("failed to download release asset: {0}")]
+    Download(#[from] CacheError),
+    #[error("failed to install dependency module `{0}`: {1}")]
+    Dependency(String, Box<InstallError>),
+    #[error("failed to record install reason of dependency module `{0}`: {1}")]
+    MarkDependency(String, io::Error),
+    #[error("failed to record secret configuration variables: {0}")]
+    MarkSecrets(io::Error),
+    #[error("failed to create directory for extracting: {0}")]
+    CreateExtractDir(io::Error),
+    #[error("failed to extract archive: {0}")]
+    Extract(io::Error),
+    #[error("module manifest does not have a choice of model size `{0}`")]
+    NoSuchModel(String),
+    #[error("error while installing required model: {0}")]
+    InstallModel(#[from] asimov_huggingface::HuggingfaceError),
+    #[error("failed to wait for the download of a required model: {0}")]
+    ModelTask(tokio::task::JoinError),
+    #[error("failed to install binary: {0}")]
+    AddBinary(#[from] registry::AddBinaryError),
+    #[error("failed to add manifest: {0}")]
+    AddManifest(#[from] registry::AddManifestError),
+}
+
+#[derive(Debug, Error)]
+pub enum ReinstallError {
+    #[error("unable to check if module is enabled: {0}")]
+    CheckEnabled(#[from] registry::IsModuleEnabledError),
+    #[error(transparent)]
+    Install(#[from] InstallError),
+    #[error(transparent)]
+    Disable(#[from] registry::DisableError),
+    #[error("unable to remove installed module binary `{0}`: {1}")]
+    RemoveBinary(String, #[source] registry::RemoveBinaryError),
+    #[error("unable to remove installed module manifest: {0}")]
+    RemoveManifest(#[from] registry::RemoveManifestError),
+    #[error("failed to re-enable module: {0}")]
+    ReEnable(#[from] registry::EnableError),
+}
+
+#[derive(Debug, Error)]
+pub enum UpgradeError {
+    #[error("failed to check the latest version of module: {0}")]
+    Fetch(#[from] LatestReleaseError),
+    #[error("unable to read current version of module: {0}")]
+    CheckVersion(#[from] registry::ModuleVersionError),
+    #[error(transparent)]
+    Reinstall(#[from] ReinstallError),
+    #[error("unable to keep previous version of module: {0}")]
+    KeepVersion(io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum RollbackError {
+    #[error("unable to read installed module manifest: {0}")]
+    ReadManifest(#[from] registry::ManifestError),
+    #[error("no previous version of module is kept")]
+    NothingKept,
+    #[error("version `{0}` of module is not kept")]
+    NoSuchVersion(String),
+    #[error("unable to check if module is enabled: {0}")]
+    CheckEnabled(#[from] registry::IsModuleEnabledError),
+    #[error("failed to restore previous version: {0}")]
+    Restore(#[from] io::Error),
+    #[error(transparent)]
+    Disable(#[from] registry::DisableError),
+    #[error("failed to re-enable module: {0}")]
+    ReEnable(#[from] registry::EnableError),
+}
+
+#[derive(Debug, Error)]
+pub enum SelfUpdateError {
+    #[error("failed to fetch release: {0}")]
+    FetchRelease(FetchError),
+    #[error("failed to fetch checksum: {0}")]
+    FetchChecksum(FetchChecksumError),
+    #[error("no release with a checksum is available for platform `{0}`")]
+    NotAvailable(String),
+    #[error("failed to download release asset: {0}")]
+    Download(#[from] CacheError),
+    #[error("failed to create directory for extracting: {0}")]
+    CreateTempDir(io::Error),
+    #[error("failed to extract archive: {0}")]
+    Extract(io::Error),
+    #[error("release archive does not contain the `asimov-module` binary")]
+    MissingBinary,
+    #[error("failed to replace binary: {0}")]
+    Replace(io::Error),
+}
@@ -0,0 +1,282 @@
+// This is free and unencumbered software released into the public domain.
+
+use super::{
+    error::{FetchChecksumError, FetchError, FetchManifestError, HttpError},
+    platform::PlatformInfo,
+};
+use asimov_module::ModuleManifest;
+use serde::Deserialize;
+use std::path::Path;
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_module_manifest(
+    client: &reqwest::Client,
+    module_name: &str,
+    version: &str,
+) -> Result<ModuleManifest, FetchManifestError> {
+    let content = fetch_module_manifest_source(client, module_name, version).await?;
+
+    serde_yml::from_str(&content)
+        .inspect_err(|err| tracing::debug!(?err, ?content))
+        .map_err(Into::into)
+}
+
+/// Fetches the YAML source of the module manifest, for reading what
+/// [`ModuleManifest`] doesn't retain.
+#[tracing::instrument(skip_all)]
+pub async fn fetch_module_manifest_source(
+    client: &reqwest::Client,
+    module_name: &str,
+    version: &str,
+) -> Result<String, FetchError> {
+    let url = format!(
+        "https://raw.githubusercontent.com/asimov-modules/asimov-{module_name}-module/{version}/.asimov/module.yaml",
+    );
+
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .inspect_err(|err| tracing::debug!(?err))?;
+
+    if !response.status().is_success() {
+        Err(HttpError::NotSuccess(response.status()))?;
+    }
+
+    response
+        .text()
+        .await
+        .inspect_err(|err| tracing::debug!(?err))
+        .map_err(Into::into)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_checksum(
+    client: &reqwest::Client,
+    asset_url: &str,
+) -> Result<Option<String>, FetchChecksumError> {
+    let checksum_url = format!("{asset_url}.sha256");
+
+    let response = client
+        .get(&checksum_url)
+        .send()
+        .await
+        .inspect_err(|err| tracing::debug!(?err))?;
+
+    if response.status() == 404 {
+        return Ok(None);
+    }
+
+    if !response.status().is_success() {
+        Err(HttpError::NotSuccess(response.status()))?;
+    }
+
+    Ok(Some(
+        response
+            .text()
+            .await
+            .inspect_err(|err| tracing::debug!(?err))?
+            .trim()
+            .to_string(),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitHubRelease {
+    #[serde(default)]
+    pub tag_name: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitHubAsset {
+    pub name: String,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_release(
+    client: &reqwest::Client,
+    repo: &str,
+    version: &str,
+) -> Result<GitHubRelease, FetchError> {
+    let url = format!("https://api.github.com/repos/{repo}/releases/tags/{version}");
+    fetch_json(client, &url).await
+}
+
+/// Fetches the latest release of the repository which isn't a pre-release.
+#[tracing::instrument(skip_all)]
+pub async fn fetch_latest_release(
+    client: &reqwest::Client,
+    repo: &str,
+) -> Result<GitHubRelease, FetchError> {
+    let url = format!("https://api.github.com/repos/{repo}/releases/latest");
+    fetch_json(client, &url).await
+}
+
+/// Fetches the most recent releases of the repository, newest first.
+#[tracing::instrument(skip_all)]
+pub async fn fetch_releases(
+    client: &reqwest::Client,
+    repo: &str,
+) -> Result<Vec<GitHubRelease>, FetchError> {
+    let url = format!("https://api.github.com/repos/{repo}/releases?per_page=100");
+    fetch_json(client, &url).await
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<T, FetchError> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .inspect_err(|err| tracing::debug!(?err))?;
+
+    if !response.status().is_success() {
+        Err(HttpError::NotSuccess(response.status()))?;
+    }
+
+    let content = response
+        .text()
+        .await
+        .inspect_err(|err| tracing::debug!(?err))?;
+
+    serde_json::from_str(&content)
+        .inspect_err(|err| tracing::debug!(?err, ?content))
+        .map_err(|e| FetchError::Deserialize(e.into()))
+}
+
+/// Returns the GitHub repository of the module, e.g.,
+/// `asimov-modules/asimov-foo-module`.
+pub fn module_repo(module_name: &str) -> String {
+    format!("asimov-modules/asimov-{module_name}-module")
+}
+
+/// Returns the package name of the repository, which prefixes the names of
+/// its release assets.
+fn package_name(repo: &str) -> &str {
+    repo.rsplit('/').next().unwrap_or(repo)
+}
+
+/// Returns the targets (e.g., `linux-arm-gnu`) that the release has assets
+/// for, based on the asset file names.
+pub fn asset_targets(repo: &str, release: &GitHubRelease) -> Vec<String> {
+    let prefix = format!("{}-", package_name(repo));
+    let mut targets: Vec<String> = release
+        .assets
+        .iter()
+        .filter_map(|asset| {
+            let target = asset.name.strip_prefix(&prefix)?;
+            target
+                .strip_suffix(".tar.gz")
+                .or_else(|| target.strip_suffix(".zip"))
+                .map(String::from)
+        })
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// Returns the release asset file names to try for the platform, in order of
+/// preference.
+pub fn asset_filenames(repo: &str, platform: &PlatformInfo) -> Vec<String> {
+    let package = package_name(repo);
+    let mut targets = Vec::new();
+    if let Some(libc) = &platform.libc {
+        targets.push(format!("{}-{}-{}", platform.os, platform.arch, libc));
+    }
+    targets.push(format!("{}-{}", platform.os, platform.arch));
+
+    targets
+        .iter()
+        .flat_map(|target| ["tar.gz", "zip"].map(|ext| format!("{package}-{target}.{ext}")))
+        .collect()
+}
+
+pub fn asset_url(repo: &str, version: &str, filename: &str) -> String {
+    format!("https://github.com/{repo}/releases/download/{version}/{filename}")
+}
+
+/// Extracts the archive at `src_archive`, whose format is determined from
+/// the asset's `filename`, into `dst_dir`.
+pub async fn extract_files(
+    src_archive: impl AsRef<Path>,
+    filename: &str,
+    dst_dir: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    use std::io::{Error, Result};
+
+    let src_archive = src_archive.as_ref().to_owned();
+    let dst = dst_dir.as_ref().to_owned();
+    let filename = filename.to_owned();
+
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let asset_file = std::fs::File::open(&src_archive)?;
+        if filename.ends_with(".tar.gz") {
+            let gz = flate2::read::GzDecoder::new(asset_file);
+            let mut archive = tar::Archive::new(gz);
+            archive.unpack(&dst)?;
+        } else if filename.ends_with(".zip") {
+            let mut archive = zip::ZipArchive::new(asset_file)?;
+            archive.extract(&dst)?;
+        } else {
+            return Err(Error::other("Unsupported format"));
+        }
+        Ok(())
+    })
+    .await??;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_targets() {
+        let release: GitHubRelease = serde_json::from_str(
+            r#"{
+                "name": "1.0.0",
+                "assets": [
+                    {"name": "asimov-foo-module-linux-arm-gnu.tar.gz"},
+                    {"name": "asimov-foo-module-linux-arm-gnu.tar.gz.sha256"},
+                    {"name": "asimov-foo-module-macos-arm.tar.gz"},
+                    {"name": "asimov-foo-module-windows-x64.zip"},
+                    {"name": "README.md"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            asset_targets(&module_repo("foo"), &release),
+            vec!["linux-arm-gnu", "macos-arm", "windows-x64"]
+        );
+    }
+
+    #[test]
+    fn test_asset_filenames() {
+        let platform = PlatformInfo {
+            os: "linux".into(),
+            arch: "arm".into(),
+            libc: Some("gnu".into()),
+        };
+        assert_eq!(
+            asset_filenames(&module_repo("foo"), &platform),
+            vec![
+                "asimov-foo-module-linux-arm-gnu.tar.gz",
+                "asimov-foo-module-linux-arm-gnu.zip",
+                "asimov-foo-module-linux-arm.tar.gz",
+                "asimov-foo-module-linux-arm.zip",
+            ]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+// This is free and unencumbered software released into the public domain.
+
+use derive_more::Display;
+
+#[derive(Clone, Debug, Display)]
+#[display("{os}-{arch}{}", libc.as_ref().map(|libc| format!("-{libc}")).unwrap_or_default())]
+pub struct PlatformInfo {
+    pub os: String,
+    pub arch: String,
+    pub libc: Option<String>,
+}
+
+pub fn detect_platform() -> PlatformInfo {
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+    let os = "unknown";
+    #[cfg(target_os = "macos")]
+    let os = "macos";
+    #[cfg(target_os = "linux")]
+    let os = "linux";
+    #[cfg(target_os = "windows")]
+    let os = "windows";
+
+    #[cfg(not(any(target_arch = "aarch64", target_arch = "arm", target_arch = "x86_64")))]
+    let arch = "unknown";
+    #[cfg(any(target_arch = "aarch64", target_arch = "arm"))]
+    let arch = "arm";
+    #[cfg(all(target_arch = "x86_64", not(target_os = "windows")))]
+    let arch = "x86";
+    #[cfg(all(target_arch = "x86_64", target_os = "windows"))]
+    let arch = "x64";
+
+    #[cfg(not(any(target_env = "musl", target_env = "gnu")))]
+    let libc = None;
+    #[cfg(target_env = "musl")]
+    let libc = Some("musl".into());
+    #[cfg(target_env = "gnu")]
+    let libc = Some("gnu".into());
+
+    PlatformInfo {
+        os: os.into(),
+        arch: arch.into(),
+        libc,
+    }
+}
+
+impl PlatformInfo {
+    /// Parses either a Rust target triple (e.g., `aarch64-unknown-linux-gnu`)
+    /// or a release asset target (e.g., `linux-arm-gnu`).
+    pub fn from_target(target: &str) -> Option<Self> {
+        let parts: Vec<&str> = target.split('-').collect();
+
+        if let ["linux" | "macos" | "windows", arch, rest @ ..] = parts.as_slice() {
+            return Some(Self {
+                os: parts[0].into(),
+                arch: (*arch).into(),
+                libc: rest.first().map(|libc| (*libc).into()),
+            });
+        }
+
+        let os = if parts.contains(&"linux") {
+            "linux"
+        } else if parts.contains(&"darwin") || parts.contains(&"apple") {
+            "macos"
+        } else if parts.contains(&"windows") {
+            "windows"
+        } else {
+            return None;
+        };
+
+        let arch = match *parts.first()? {
+            "x86_64" if os == "windows" => "x64",
+            "x86_64" => "x86",
+            "aarch64" | "arm64" => "arm",
+            arch if arch.starts_with("arm") => "arm",
+            _ => return None,
+        };
+
+        let libc = parts.last().and_then(|env| {
+            if env.starts_with("gnu") {
+                Some("gnu".into())
+            } else if env.starts_with("musl") {
+                Some("musl".into())
+            } else {
+                None
+            }
+        });
+
+        Some(Self {
+            os: os.into(),
+            arch: arch.into(),
+            libc,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_target() {
+        let target = |s| PlatformInfo::from_target(s).map(|p| p.to_string());
+        assert_eq!(
+            target("aarch64-unknown-linux-gnu").as_deref(),
+            Some("linux-arm-gnu")
+        );
+        assert_eq!(
+            target("x86_64-unknown-linux-musl").as_deref(),
+            Some("linux-x86-musl")
+        );
+        assert_eq!(target("aarch64-apple-darwin").as_deref(), Some("macos-arm"));
+        assert_eq!(
+            target("x86_64-pc-windows-msvc").as_deref(),
+            Some("windows-x64")
+        );
+        assert_eq!(target("linux-arm-gnu").as_deref(), Some("linux-arm-gnu"));
+        assert_eq!(target("riscv64gc-unknown-linux-gnu"), None);
+        assert_eq!(target("foobar"), None);
+    }
+}
@@ -0,0 +1,183 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Updates of the `asimov-module` binary itself, from the CLI's GitHub
+//! releases. The previous binary is kept next to the new one, with an `.old`
+//! suffix, for rolling back.
+
+use super::{Installer, error::SelfUpdateError, github, platform::PlatformInfo};
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The GitHub repository of the CLI.
+pub const CLI_REPO: &str = "asimov-platform/asimov-module-cli";
+
+/// The name of the CLI binary within the release archives.
+const CLI_BINARY: &str = "asimov-module";
+
+impl Installer {
+    /// Fetches the version of the latest stable release of the CLI.
+    pub async fn fetch_latest_cli_version(&self) -> Result<String, SelfUpdateError> {
+        let release = github::fetch_latest_release(&self.client, CLI_REPO)
+            .await
+            .map_err(SelfUpdateError::FetchRelease)?;
+        Ok(release.tag_name)
+    }
+
+    /// Downloads the given version of the CLI for the platform, verifies its
+    /// checksum, and atomically replaces the binary at `exe` with it. The
+    /// replaced binary is kept at [`rollback_path`].
+    pub async fn update_cli(
+        &self,
+        version: &str,
+        platform: &PlatformInfo,
+        exe: &Path,
+    ) -> Result<(), SelfUpdateError> {
+        let (filename, archive) = self.download_verified_cli(version, platform).await?;
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("asimov-module-self-update")
+            .tempdir()
+            .map_err(SelfUpdateError::CreateTempDir)?;
+        github::extract_files(&archive, &filename, temp_dir.path())
+            .await
+            .map_err(SelfUpdateError::Extract)?;
+
+        let binary = temp_dir.path().join(CLI_BINARY);
+        #[cfg(windows)]
+        let binary = binary.with_extension("exe");
+        if !tokio::fs::try_exists(&binary).await.unwrap_or(false) {
+            return Err(SelfUpdateError::MissingBinary);
+        }
+
+        replace_binary(&binary, exe)
+            .await
+            .map_err(SelfUpdateError::Replace)
+    }
+
+    /// Fetches the release archive of the CLI for the platform, only
+    /// accepting archives that have a published checksum.
+    async fn download_verified_cli(
+        &self,
+        version: &str,
+        platform: &PlatformInfo,
+    ) -> Result<(String, PathBuf), SelfUpdateError> {
+        for filename in github::asset_filenames(CLI_REPO, platform) {
+            let url = github::asset_url(CLI_REPO, version, &filename);
+
+            let Some(checksum) = github::fetch_checksum(&self.client, &url)
+                .await
+                .map_err(SelfUpdateError::FetchChecksum)?
+            else {
+                tracing::debug!("no checksum published for {url}, skipping");
+                continue;
+            };
+
+            if let Some(path) = self
+                .cache
+                .fetch(&self.client, &url, Some(&checksum))
+                .await?
+            {
+                return Ok((filename, path));
+            }
+        }
+
+        Err(SelfUpdateError::NotAvailable(platform.to_string()))
+    }
+}
+
+/// Returns the path of the previous binary kept for rolling back.
+pub fn rollback_path(exe: &Path) -> PathBuf {
+    let mut path = OsString::from(exe.as_os_str());
+    path.push(".old");
+    PathBuf::from(path)
+}
+
+/// Swaps the binary at `exe` with the one kept at [`rollback_path`], so that
+/// rolling back can itself be undone.
+pub async fn rollback_cli(exe: &Path) -> io::Result<()> {
+    let rollback = rollback_path(exe);
+    if !tokio::fs::try_exists(&rollback).await? {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no previous version is kept",
+        ));
+    }
+
+    let staging = staging_path(exe);
+    tokio::fs::copy(&rollback, &staging).await?;
+    replace_binary_with_staged(&staging, exe).await
+}
+
+/// Replaces the binary at `exe` with `new_binary`, keeping the replaced one
+/// at [`rollback_path`].
+async fn replace_binary(new_binary: &Path, exe: &Path) -> io::Result<()> {
+    // Stage the new binary in the same directory, so that it can be renamed
+    // into place atomically:
+    let staging = staging_path(exe);
+    tokio::fs::copy(new_binary, &staging).await?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&staging, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+
+    replace_binary_with_staged(&staging, exe).await
+}
+
+async fn replace_binary_with_staged(staging: &Path, exe: &Path) -> io::Result<()> {
+    let rollback = rollback_path(exe);
+
+    // A running binary can't be overwritten on Windows, but can be renamed:
+    #[cfg(windows)]
+    let result = async {
+        let _ = tokio::fs::remove_file(&rollback).await;
+        tokio::fs::rename(exe, &rollback).await?;
+        tokio::fs::rename(staging, exe).await
+    }
+    .await;
+
+    #[cfg(not(windows))]
+    let result = async {
+        tokio::fs::copy(exe, &rollback).await?;
+        tokio::fs::rename(staging, exe).await
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(staging).await;
+    }
+    result
+}
+
+fn staging_path(exe: &Path) -> PathBuf {
+    let mut path = OsString::from(exe.as_os_str());
+    path.push(".new");
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replace_and_rollback() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let exe = dir.child("asimov-module");
+        let new_binary = dir.child("downloaded");
+        tokio::fs::write(&exe, "old").await.unwrap();
+        tokio::fs::write(&new_binary, "new").await.unwrap();
+
+        replace_binary(&new_binary, &exe).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(rollback_path(&exe)).unwrap(), "old");
+        assert!(!staging_path(&exe).exists());
+
+        rollback_cli(&exe).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "old");
+        assert_eq!(std::fs::read_to_string(rollback_path(&exe)).unwrap(), "new");
+    }
+}
@@ -0,0 +1,337 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Previously installed versions of modules, kept on disk for rollback under
+//! `~/.asimov/modules/versions/<module>/<version>/`.
+
+use asimov_env::paths::asimov_root;
+use asimov_module::InstalledModuleManifest;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The number of previous versions kept per module, unless configured.
+pub const DEFAULT_RETENTION: usize = 1;
+
+#[derive(Clone, Debug)]
+pub struct KeptVersion {
+    pub version: String,
+    pub path: PathBuf,
+    pub kept_at: Option<SystemTime>,
+}
+
+impl KeptVersion {
+    pub async fn read_manifest(&self) -> io::Result<InstalledModuleManifest> {
+        let content = tokio::fs::read(self.path.join("manifest.json")).await?;
+        serde_json::from_slice(&content).map_err(io::Error::other)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Versions {
+    dir: PathBuf,
+    install_dir: PathBuf,
+    exec_dir: PathBuf,
+}
+
+impl Default for Versions {
+    fn default() -> Self {
+        let root = asimov_root();
+        Self::new(
+            root.join("modules").join("versions"),
+            root.join("modules").join("installed"),
+            root.join("libexec"),
+        )
+    }
+}
+
+impl Versions {
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        install_dir: impl Into<PathBuf>,
+        exec_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            dir: dir.into(),
+            install_dir: install_dir.into(),
+            exec_dir: exec_dir.into(),
+        }
+    }
+
+    pub fn exec_dir(&self) -> &Path {
+        &self.exec_dir
+    }
+
+    /// Keeps a copy of the currently installed files of the module.
+    pub async fn save(&self, manifest: &InstalledModuleManifest) -> io::Result<KeptVersion> {
+        let version = manifest.version.as_deref().unwrap_or("unknown");
+        let path = self.dir.join(&manifest.manifest.name).join(version);
+
+        // Stage the copy first, so that an interrupted save is never mistaken
+        // for a complete one:
+        let staging = path.with_added_extension("partial");
+        remove_dir_if_exists(&staging).await?;
+        tokio::fs::create_dir_all(&staging).await?;
+
+        for program in &manifest.manifest.provides.programs {
+            let src = self.exec_dir.join(program);
+            if !tokio::fs::try_exists(&src).await? {
+                tracing::debug!(?src, "program to keep is missing, skipping");
+                continue;
+            }
+            tokio::fs::copy(&src, staging.join(program)).await?;
+        }
+
+        let serialized = serde_json::to_vec_pretty(manifest).map_err(io::Error::other)?;
+        tokio::fs::write(staging.join("manifest.json"), serialized).await?;
+
+        remove_dir_if_exists(&path).await?;
+        tokio::fs::rename(&staging, &path).await?;
+
+        Ok(KeptVersion {
+            version: version.into(),
+            path,
+            kept_at: Some(SystemTime::now()),
+        })
+    }
+
+    /// Returns the kept versions of the module, most recently kept first.
+    pub async fn list(&self, module_name: &str) -> io::Result<Vec<KeptVersion>> {
+        let dir = self.dir.join(module_name);
+        let mut read_dir = match tokio::fs::read_dir(&dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut versions = Vec::new();
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            if !entry.file_type().await?.is_dir()
+                || path.extension().is_some_and(|ext| ext == "partial")
+            {
+                continue;
+            }
+            versions.push(KeptVersion {
+                version: entry.file_name().to_string_lossy().into_owned(),
+                kept_at: entry.metadata().await?.modified().ok(),
+                path,
+            });
+        }
+
+        versions.sort_by_key(|kept| std::cmp::Reverse(kept.kept_at));
+        Ok(versions)
+    }
+
+    /// Returns the names of the modules that have kept versions.
+    pub async fn modules(&self) -> io::Result<Vec<String>> {
+        let mut read_dir = match tokio::fs::read_dir(&self.dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut modules = Vec::new();
+        while let Some(entry) = read_dir.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                modules.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        modules.sort();
+        Ok(modules)
+    }
+
+    /// Puts the kept version back in place of the currently installed files,
+    /// given the currently installed manifest, if any. The kept copy is
+    /// consumed.
+    ///
+    /// All files are first staged next to their destinations and then renamed
+    /// over them, so that the module is never left with a mix of versions.
+    pub async fn restore(
+        &self,
+        kept: &KeptVersion,
+        current: Option<&InstalledModuleManifest>,
+    ) -> io::Result<InstalledModuleManifest> {
+        let manifest = kept.read_manifest().await?;
+        let module_name = &manifest.manifest.name;
+
+        let mut staged = Vec::new();
+        let result = self.stage(kept, &manifest, &mut staged).await;
+        if let Err(err) = result {
+            for (staging, _) in staged {
+                let _ = tokio::fs::remove_file(staging).await;
+            }
+            return Err(err);
+        }
+
+        for (staging, target) in &staged {
+            tokio::fs::rename(staging, target).await?;
+        }
+
+        // Remove programs which the restored version doesn't provide:
+        if let Some(current) = current {
+            for program in &current.manifest.provides.programs {
+                if !manifest.manifest.provides.programs.contains(program) {
+                    remove_file_if_exists(&self.exec_dir.join(program)).await?;
+                }
+            }
+        }
+        for ext in ["yaml", "yml"] {
+            remove_file_if_exists(&self.install_dir.join(module_name).with_extension(ext)).await?;
+        }
+
+        tokio::fs::remove_dir_all(&kept.path).await?;
+
+        Ok(manifest)
+    }
+
+    async fn stage(
+        &self,
+        kept: &KeptVersion,
+        manifest: &InstalledModuleManifest,
+        staged: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> io::Result<()> {
+        for program in &manifest.manifest.provides.programs {
+            let staging = self.exec_dir.join(format!(".{program}.rollback"));
+            staged.push((staging.clone(), self.exec_dir.join(program)));
+            tokio::fs::copy(kept.path.join(program), &staging).await?;
+        }
+
+        let module_name = &manifest.manifest.name;
+        let staging = self
+            .install_dir
+            .join(format!(".{module_name}.json.rollback"));
+        staged.push((
+            staging.clone(),
+            self.install_dir.join(module_name).with_extension("json"),
+        ));
+        let serialized = serde_json::to_vec_pretty(manifest).map_err(io::Error::other)?;
+        tokio::fs::write(&staging, serialized).await?;
+
+        Ok(())
+    }
+
+    /// Removes all but the `keep` most recently kept versions of the module.
+    pub async fn prune(&self, module_name: &str, keep: usize) -> io::Result<()> {
+        for version in self.list(module_name).await?.into_iter().skip(keep) {
+            tracing::debug!(module_name, version.version, "removing kept version");
+            tokio::fs::remove_dir_all(&version.path).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes all kept versions of the module.
+    pub async fn remove_all(&self, module_name: &str) -> io::Result<()> {
+        remove_dir_if_exists(&self.dir.join(module_name)).await
+    }
+}
+
+async fn remove_file_if_exists(path: &Path) -> io::Result<()> {
+    tokio::fs::remove_file(path).await.or_else(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            Ok(())
+        } else {
+            Err(e)
+        }
+    })
+}
+
+async fn remove_dir_if_exists(path: &Path) -> io::Result<()> {
+    tokio::fs::remove_dir_all(path).await.or_else(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            Ok(())
+        } else {
+            Err(e)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(version: &str, programs: &[&str]) -> InstalledModuleManifest {
+        serde_json::from_value(serde_json::json!({
+            "version": version,
+            "name": "foo",
+            "label": "Foo",
+            "summary": "",
+            "links": [],
+            "provides": { "programs": programs },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_save_list_prune() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let versions = Versions::new(
+            dir.child("versions"),
+            dir.child("installed"),
+            dir.child("libexec"),
+        );
+
+        tokio::fs::create_dir_all(versions.exec_dir())
+            .await
+            .unwrap();
+        tokio::fs::write(versions.exec_dir().join("asimov-foo-reader"), "v1")
+            .await
+            .unwrap();
+
+        for version in ["1.0.0", "1.1.0", "1.2.0"] {
+            let manifest = manifest(version, &["asimov-foo-reader"]);
+            versions.save(&manifest).await.unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let kept = versions.list("foo").await.unwrap();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].version, "1.2.0");
+        assert!(kept[0].path.join("asimov-foo-reader").exists());
+        assert_eq!(
+            kept[0].read_manifest().await.unwrap().version.as_deref(),
+            Some("1.2.0")
+        );
+
+        versions.prune("foo", 1).await.unwrap();
+        let kept = versions.list("foo").await.unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].version, "1.2.0");
+    }
+
+    #[tokio::test]
+    async fn test_restore() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let versions = Versions::new(
+            dir.child("versions"),
+            dir.child("installed"),
+            dir.child("libexec"),
+        );
+        tokio::fs::create_dir_all(dir.child("installed"))
+            .await
+            .unwrap();
+        tokio::fs::create_dir_all(versions.exec_dir())
+            .await
+            .unwrap();
+
+        let program = versions.exec_dir().join("asimov-foo-reader");
+        tokio::fs::write(&program, "v1").await.unwrap();
+
+        let old = manifest("1.0.0", &["asimov-foo-reader"]);
+        let kept = versions.save(&old).await.unwrap();
+
+        let current = manifest("2.0.0", &["asimov-foo-reader", "asimov-foo-writer"]);
+        tokio::fs::write(&program, "v2").await.unwrap();
+        tokio::fs::write(versions.exec_dir().join("asimov-foo-writer"), "v2")
+            .await
+            .unwrap();
+
+        let restored = versions.restore(&kept, Some(&current)).await.unwrap();
+        assert_eq!(restored.version.as_deref(), Some("1.0.0"));
+        assert_eq!(std::fs::read_to_string(&program).unwrap(), "v1");
+        assert!(!versions.exec_dir().join("asimov-foo-writer").exists());
+        assert!(dir.child("installed").join("foo.json").exists());
+        assert!(versions.list("foo").await.unwrap().is_empty());
+    }
+}
@@ -1,11 +1,68 @@
 // This is free and unencumbered software released into the public domain.
 
+pub mod cache;
 pub mod commands;
+pub mod dependencies;
 pub mod features;
+pub mod installer;
+pub mod models;
 pub mod options {}
+pub mod pins;
+pub mod policy;
+pub mod profiles;
+pub mod reasons;
 pub mod registry;
+pub mod secrets;
+pub mod selectors;
+pub mod settings;
+pub mod store;
+pub mod variables;
 
 use clientele::{StandardOptions, SysexitsError};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Returns whether both stdin and stdout are attached to a terminal, i.e.,
+/// whether it is appropriate to prompt the user for input.
+pub(crate) fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+/// Asks the user a yes/no question on the terminal, returning `default` when
+/// the user just presses Enter.
+pub(crate) fn confirm(question: &str, default: bool) -> std::io::Result<bool> {
+    let choices = if default { "[Y/n]" } else { "[y/N]" };
+
+    let mut stdout = std::io::stdout().lock();
+    write!(&mut stdout, "{question} {choices} ")?;
+    stdout.flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(match answer.trim().to_ascii_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    })
+}
+
+/// Formats a byte count for display, e.g., `1.5 GiB`.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
 
 /// Sorts links from a module's manifest in the order that we'd like to display
 /// them for the command `link` and for choosing the URL to open for the command
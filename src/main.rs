@@ -2,13 +2,17 @@
 
 #![deny(unsafe_code)]
 
-use asimov_module_cli::commands;
+use asimov_module_cli::{
+    commands, policy::UpgradePolicy, reasons::InstallReason, registry::ModuleType,
+    selectors::Selection,
+};
 
 use clientele::{
     StandardOptions,
     SysexitsError::{self, *},
-    crates::clap::{Parser, Subcommand},
+    crates::clap::{ArgGroup, Args, Parser, Subcommand},
 };
+use std::path::PathBuf;
 
 /// ASIMOV Module Command-Line Interface (CLI)
 #[derive(Debug, Parser)]
@@ -18,12 +22,25 @@ struct Options {
     #[clap(flatten)]
     flags: StandardOptions,
 
+    /// The configuration profile for the `config` commands [default: the
+    /// selected profile]
+    #[arg(value_name = "PROFILE", long, global = true, env = "ASIMOV_PROFILE")]
+    profile: Option<String>,
+
     #[clap(subcommand)]
     command: Option<Command>,
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
+    /// Uninstall modules which were installed as dependencies and which no
+    /// installed module requires anymore
+    Autoremove {
+        /// Only print the modules which would be uninstalled
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
     /// Open the module's package page in a web browser
     #[clap(alias = "open")]
     Browse {
@@ -31,16 +48,62 @@ enum Command {
         name: String,
     },
 
+    /// Manage the download cache
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Show the release notes of a module
+    ///
+    /// By default shows the releases newer than the installed version.
+    Changelog {
+        /// The name of the module
+        name: String,
+
+        /// Show releases newer than this version [default: installed version]
+        #[arg(value_name = "VERSION", long)]
+        from: Option<String>,
+
+        /// Show releases up to and including this version [default: latest]
+        #[arg(value_name = "VERSION", long)]
+        to: Option<String>,
+    },
+
     /// Configure an installed module
-    #[clap(override_usage = CONFIG_USAGE)]
+    #[clap(
+        override_usage = CONFIG_USAGE,
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
     Config {
-        /// The name of the module to configure
-        name: String,
+        #[command(subcommand)]
+        command: Option<ConfigCommand>,
+
+        /// The name of the module to configure, or a shell-style pattern
+        /// such as `'openai*'`
+        #[arg(required_unless_present = "list")]
+        name: Option<String>,
 
         /// Unset configured variable(s). By default all when no arguments provided.
         #[arg(short = 'u', long, default_value = "false")]
         unset: bool,
 
+        /// Mark the given variable(s) as secret, storing their values encrypted.
+        /// Only variables with an environment variable can be secret, as
+        /// modules receive the decrypted values through it.
+        #[arg(long, requires = "args", conflicts_with = "unset")]
+        secret: bool,
+
+        /// List the variables with their effective values and where they
+        /// come from, for all modules unless a module is given.
+        #[arg(short = 'l', long, conflicts_with_all = ["unset", "secret", "args"])]
+        list: bool,
+
+        /// Set the output format of `--list` [default: cli] [possible values: cli, json, jsonl]
+        #[arg(value_name = "FORMAT", short = 'o', long, requires = "list")]
+        output: Option<String>,
+
         /// A single configuration variable to read, or key-value pair(s) to be set.
         #[clap(trailing_var_arg = true)]
         args: Vec<String>,
@@ -48,14 +111,51 @@ enum Command {
 
     /// Disable modules
     Disable {
-        /// The names of the modules to disable
-        names: Vec<String>,
+        #[command(flatten)]
+        modules: ModuleSelector,
+
+        /// Also disable the enabled modules which require the modules
+        #[arg(long)]
+        cascade: bool,
     },
 
+    /// Check the installation for problems
+    ///
+    /// Currently checks that configuration files aren't readable by other
+    /// users.
+    Doctor,
+
     /// Enable modules
+    ///
+    /// Also enables the modules they require, offering to install missing
+    /// ones. Modules with missing required configuration are only enabled
+    /// with `--force`.
     Enable {
-        /// The names of the modules to enable
-        names: Vec<String>,
+        #[command(flatten)]
+        modules: ModuleSelector,
+
+        /// Enable modules even if their configuration is missing
+        #[arg(short = 'f', long)]
+        force: bool,
+
+        /// Don't offer to configure modules with missing configuration
+        #[arg(long)]
+        no_prompt: bool,
+    },
+
+    /// Run a command with the secret configuration of modules
+    ///
+    /// Modules can't decrypt the values of their secret variables, and only
+    /// receive them through their environment variables, which this sets
+    /// for the command, e.g., `asimov module exec <module> -- asimov ...`.
+    Exec {
+        /// The modules whose secret variables to pass
+        #[arg(required = true)]
+        modules: Vec<String>,
+
+        /// The command to run, along with its arguments
+        #[arg(value_name = "COMMAND", last = true, required = true)]
+        command: Vec<String>,
     },
 
     /// TBD
@@ -76,8 +176,8 @@ enum Command {
 
     /// Install an available module locally
     Install {
-        /// The names of the modules to install
-        names: Vec<String>,
+        #[command(flatten)]
+        modules: ModuleSelector,
 
         /// Optionally install a specific version instead of latest
         #[arg(long)]
@@ -87,6 +187,30 @@ enum Command {
         /// Only affects modules which require models.
         #[arg(long)]
         model_size: Option<String>,
+
+        /// Reinstall modules which are already installed
+        #[arg(short = 'f', long)]
+        force: bool,
+
+        /// Don't offer to configure modules with missing configuration
+        #[arg(long)]
+        no_prompt: bool,
+
+        /// Consider pre-releases, overriding the configured release channel
+        #[arg(long, conflicts_with = "version")]
+        pre: bool,
+
+        /// Only download the release archives, without installing them
+        #[arg(long)]
+        download_only: bool,
+
+        /// The target to download for, e.g., `aarch64-unknown-linux-gnu`
+        #[arg(value_name = "TRIPLE", long, requires = "download_only")]
+        target: Option<String>,
+
+        /// The directory to download into [default: .]
+        #[arg(value_name = "DIR", short = 'o', long, requires = "download_only")]
+        output: Option<PathBuf>,
     },
 
     /// Print the module's package link
@@ -104,15 +228,157 @@ enum Command {
         output: Option<String>,
     },
 
+    /// Mark modules as installed manually or as dependencies
+    ///
+    /// Modules installed as dependencies are uninstalled by `autoremove` once
+    /// no installed module requires them.
+    #[command(group(ArgGroup::new("reason").required(true)))]
+    Mark {
+        /// The names of the modules to mark
+        #[arg(required = true)]
+        names: Vec<String>,
+
+        /// Mark the modules as installed as dependencies
+        #[arg(long, group = "reason")]
+        auto: bool,
+
+        /// Mark the modules as installed explicitly
+        #[arg(long, group = "reason")]
+        manual: bool,
+    },
+
+    /// Manage the models downloaded for modules
+    Models {
+        #[clap(subcommand)]
+        command: ModelsCommand,
+    },
+
+    /// List installed modules which have newer releases
+    Outdated {
+        /// Consider pre-releases, overriding the configured release channel
+        #[arg(long)]
+        pre: bool,
+    },
+
+    /// Pin modules, holding them at a version on upgrade
+    ///
+    /// Without a version, modules are pinned at their installed version.
+    /// Without any modules, lists the pinned modules.
+    Pin {
+        /// The modules to pin, as `<name>` or `<name>@<version>`
+        #[arg(value_name = "NAME[@VERSION]")]
+        names: Vec<String>,
+    },
+
+    /// Manage the configuration profiles
+    ///
+    /// Each profile holds its own set of configuration values, e.g., for
+    /// separate staging and production credentials, which the `config`
+    /// commands read and write. Modules themselves only read the `default`
+    /// profile; to apply another profile, export it and import it there.
+    Profile {
+        #[clap(subcommand)]
+        command: ProfileCommand,
+    },
+
+    /// Reinstall currently installed modules, e.g., to repair them
+    ///
+    /// Keeps the modules' configuration and enabled state.
+    Reinstall {
+        /// The names of the modules to reinstall
+        names: Vec<String>,
+
+        /// Optionally reinstall a specific version instead of latest
+        #[arg(long, conflicts_with = "keep_version")]
+        version: Option<String>,
+
+        /// Reinstall the currently installed version instead of latest
+        #[arg(long)]
+        keep_version: bool,
+
+        /// Optionally specify desired model size to download for module.
+        /// Only affects modules which require models.
+        #[arg(long)]
+        model_size: Option<String>,
+    },
+
     /// Resolve a given URL to modules which can handle it
     Resolve {
         /// The URL to resolve
         url: String,
     },
 
+    /// Roll back a module to a previously installed version
+    ///
+    /// Previous versions are kept when upgrading, see `upgrade --keep-versions`.
+    Rollback {
+        /// The name of the module to roll back
+        #[arg(required_unless_present = "list")]
+        name: Option<String>,
+
+        /// Roll back to a specific kept version instead of the most recent
+        #[arg(long, value_name = "VERSION", conflicts_with = "list")]
+        to: Option<String>,
+
+        /// List the kept versions instead of rolling back
+        #[arg(short = 'l', long)]
+        list: bool,
+    },
+
+    /// Update this CLI to its latest release
+    SelfUpdate {
+        /// Only check for a newer release, exiting with status 75
+        /// (EX_TEMPFAIL) if there is one
+        #[arg(long, conflicts_with = "rollback")]
+        check: bool,
+
+        /// Restore the version replaced by the last update
+        #[arg(long)]
+        rollback: bool,
+    },
+
+    /// Show or change the settings of this CLI
+    ///
+    /// Without a key, lists all settings. Known settings:
+    /// `channel` (stable, dev), `upgrade-policy` (patch, minor, major).
+    Settings {
+        /// The setting to show or change
+        key: Option<String>,
+
+        /// The value to set the setting to
+        value: Option<String>,
+
+        /// Apply to a module instead of globally
+        #[arg(value_name = "NAME", short = 'm', long)]
+        module: Option<String>,
+
+        /// Remove the setting
+        #[arg(long, requires = "key", conflicts_with = "value")]
+        unset: bool,
+    },
+
     /// Uninstall a currently installed module
     Uninstall {
-        /// The names of the modules to uninstall
+        #[command(flatten)]
+        modules: ModuleSelector,
+
+        /// Also remove configuration, models and downloads of the modules
+        #[arg(long)]
+        purge: bool,
+
+        /// Also uninstall the modules which require the modules
+        #[arg(long, conflicts_with = "force")]
+        cascade: bool,
+
+        /// Uninstall even if other modules require the modules
+        #[arg(short = 'f', long)]
+        force: bool,
+    },
+
+    /// Unpin modules, allowing them to be upgraded again
+    Unpin {
+        /// The names of the modules to unpin
+        #[arg(required = true)]
         names: Vec<String>,
     },
 
@@ -121,8 +387,8 @@ enum Command {
     /// By default upgrades all installed modules.
     #[clap(alias = "update")]
     Upgrade {
-        /// The names of the modules to upgrade
-        names: Vec<String>,
+        #[command(flatten)]
+        modules: ModuleSelector,
 
         /// Optionally upgrade to a specific version instead of latest
         #[arg(long)]
@@ -132,6 +398,200 @@ enum Command {
         /// Only affects modules which require models.
         #[arg(long)]
         model_size: Option<String>,
+
+        /// Only check for pending upgrades, exiting with status 75
+        /// (EX_TEMPFAIL) if there are any
+        #[arg(long, conflicts_with = "dry_run")]
+        check: bool,
+
+        /// Print the planned upgrades without changing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// The number of previous versions to keep for rolling back
+        #[arg(
+            long,
+            value_name = "N",
+            env = "ASIMOV_MODULE_KEEP_VERSIONS",
+            default_value_t = asimov_module_cli::installer::versions::DEFAULT_RETENTION
+        )]
+        keep_versions: usize,
+
+        /// Show the release notes of the upgrades, asking for confirmation
+        /// when running in a terminal
+        #[arg(long, conflicts_with = "check")]
+        changes: bool,
+
+        /// Upgrade pinned modules too
+        #[arg(long)]
+        ignore_pins: bool,
+
+        /// Consider pre-releases, overriding the configured release channel
+        #[arg(long, conflicts_with = "version")]
+        pre: bool,
+
+        /// The number of modules to upgrade at the same time
+        #[arg(value_name = "N", short = 'j', long, default_value_t = 4)]
+        jobs: usize,
+
+        /// Only upgrade to newer patch releases, e.g., from 1.2.3 to 1.2.4
+        #[arg(long, group = "policy")]
+        patch: bool,
+
+        /// Only upgrade to newer minor or patch releases (the default)
+        #[arg(long, group = "policy")]
+        minor: bool,
+
+        /// Allow upgrading to newer major releases
+        #[arg(long, group = "policy")]
+        major: bool,
+    },
+}
+
+/// Selects modules by name, by pattern, by type, or all of them.
+#[derive(Debug, Args)]
+struct ModuleSelector {
+    /// The names of the modules, or shell-style patterns such as `'openai*'`
+    names: Vec<String>,
+
+    /// Select all modules
+    #[arg(long, conflicts_with = "names")]
+    all: bool,
+
+    /// Only select modules of this type [possible values: rust, ruby, python]
+    #[arg(value_name = "TYPE", long = "type")]
+    module_type: Option<ModuleType>,
+}
+
+impl From<ModuleSelector> for Selection {
+    fn from(selector: ModuleSelector) -> Self {
+        Self {
+            names: selector.names,
+            all: selector.all,
+            module_type: selector.module_type,
+            quiet: false,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Export the configuration of modules, e.g., to set up another machine
+    Export {
+        /// The modules to export, or shell-style patterns [default: all]
+        modules: Vec<String>,
+
+        /// Set the file format [default: from the file extension, or yaml] [possible values: yaml, json, env]
+        #[arg(value_name = "FORMAT", short = 'f', long)]
+        format: Option<String>,
+
+        /// The file to write to [default: standard output]
+        #[arg(value_name = "FILE", short = 'o', long)]
+        output: Option<PathBuf>,
+
+        /// Include the values of secret variables, decrypted and marked as
+        /// secret so that importing them stores them encrypted again
+        #[arg(long)]
+        secrets: bool,
+    },
+
+    /// Import the configuration of modules exported with `config export`
+    ///
+    /// Every variable is checked against the manifests of the installed
+    /// modules before any is written.
+    Import {
+        /// The file to import
+        file: PathBuf,
+
+        /// Set the file format [default: from the file extension] [possible values: yaml, json, env]
+        #[arg(value_name = "FORMAT", short = 'f', long)]
+        format: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CacheCommand {
+    /// List the cached downloads
+    #[clap(alias = "ls")]
+    List,
+
+    /// Remove cached downloads which haven't been used recently
+    Prune {
+        /// Remove downloads unused for at least this many days
+        #[arg(value_name = "DAYS", long, default_value = "30")]
+        max_age: u64,
+    },
+
+    /// Remove all cached downloads
+    Clear,
+}
+
+#[derive(Debug, Subcommand)]
+enum ModelsCommand {
+    /// List the available model sizes of a module and their download sizes
+    #[clap(alias = "ls")]
+    List {
+        /// The name of the module
+        name: String,
+    },
+
+    /// Download a module's models, e.g., to switch to another model size
+    Pull {
+        /// The name of the module
+        name: String,
+
+        /// The model size to download. By default the first available size.
+        size: Option<String>,
+    },
+
+    /// Remove a module's downloaded models
+    #[clap(alias = "rm")]
+    Remove {
+        /// The name of the module
+        name: String,
+
+        /// The model size to remove. By default all sizes.
+        size: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfileCommand {
+    /// List the profiles, marking the one in use
+    #[clap(alias = "ls")]
+    List,
+
+    /// Create an empty profile
+    Create {
+        /// The name of the profile
+        name: String,
+    },
+
+    /// Create a profile with a copy of another profile's configuration
+    #[clap(alias = "cp")]
+    Copy {
+        /// The name of the profile to copy
+        from: String,
+
+        /// The name of the profile to create
+        to: String,
+    },
+
+    /// Delete a profile along with its configuration
+    #[clap(alias = "rm")]
+    Delete {
+        /// The name of the profile
+        name: String,
+    },
+
+    /// Use the profile for the `config` commands unless another is given
+    /// with `--profile`
+    ///
+    /// Modules themselves keep reading their configuration from the
+    /// `default` profile, as do `install` and `enable` when checking it.
+    Use {
+        /// The name of the profile
+        name: String,
     },
 }
 
@@ -176,32 +636,205 @@ pub fn main() -> SysexitsError {
 
     // Execute the given command:
     let result = match options.command.unwrap() {
+        Command::Autoremove { dry_run } => commands::autoremove(dry_run, &options.flags),
         Command::Browse { name } => commands::browse(name, &options.flags),
-        Command::Config { name, unset, args } => {
-            commands::config(name, unset, &args, &options.flags)
+        Command::Cache { command } => match command {
+            CacheCommand::List => commands::cache_list(&options.flags),
+            CacheCommand::Prune { max_age } => commands::cache_prune(max_age, &options.flags),
+            CacheCommand::Clear => commands::cache_clear(&options.flags),
+        },
+        Command::Changelog { name, from, to } => {
+            commands::changelog(name, from, to, &options.flags)
+        },
+        Command::Config {
+            command: Some(command),
+            ..
+        } => match command {
+            ConfigCommand::Export {
+                modules,
+                format,
+                output,
+                secrets,
+            } => commands::config_export(
+                modules,
+                format,
+                output,
+                secrets,
+                options.profile,
+                &options.flags,
+            ),
+            ConfigCommand::Import { file, format } => {
+                commands::config_import(file, format, options.profile, &options.flags)
+            },
         },
-        Command::Disable { names } => commands::disable(names, &options.flags),
-        Command::Enable { names } => commands::enable(names, &options.flags),
+        Command::Config {
+            name,
+            list: true,
+            output,
+            ..
+        } => commands::config_list(
+            name,
+            output.as_deref().unwrap_or("cli"),
+            options.profile,
+            &options.flags,
+        ),
+        Command::Config {
+            name,
+            unset,
+            secret,
+            args,
+            ..
+        } => commands::config(
+            name.unwrap_or_default(),
+            unset,
+            secret,
+            &args,
+            options.profile,
+            &options.flags,
+        ),
+        Command::Disable { modules, cascade } => {
+            commands::disable(modules.into(), cascade, &options.flags)
+        },
+        Command::Doctor => commands::doctor(&options.flags),
+        Command::Enable {
+            modules,
+            force,
+            no_prompt,
+        } => commands::enable(
+            modules.into(),
+            force,
+            no_prompt,
+            options.profile,
+            &options.flags,
+        ),
+        Command::Exec { modules, command } => commands::exec(modules, command, &options.flags),
         #[cfg(feature = "unstable")]
         Command::Find { name } => commands::find(name, &options.flags),
         #[cfg(feature = "unstable")]
         Command::Inspect { name } => commands::inspect(name, &options.flags),
         Command::Install {
-            names,
+            modules,
             version,
             model_size,
-        } => commands::install(names, version, model_size, &options.flags),
+            force,
+            no_prompt,
+            pre,
+            download_only,
+            target,
+            output,
+        } => {
+            if download_only {
+                commands::download(modules.into(), version, target, output, pre, &options.flags)
+            } else {
+                commands::install(
+                    modules.into(),
+                    version,
+                    model_size,
+                    force,
+                    no_prompt,
+                    pre,
+                    options.profile,
+                    &options.flags,
+                )
+            }
+        },
         Command::Link { name } => commands::link(name, &options.flags),
         Command::List { output } => {
-            commands::list(output.as_deref().unwrap_or(&"cli"), &options.flags)
+            commands::list(output.as_deref().unwrap_or("cli"), &options.flags)
+        },
+        Command::Mark { names, auto, .. } => commands::mark(
+            names,
+            if auto {
+                InstallReason::Auto
+            } else {
+                InstallReason::Manual
+            },
+            &options.flags,
+        ),
+        Command::Models { command } => match command {
+            ModelsCommand::List { name } => commands::models_list(name, &options.flags),
+            ModelsCommand::Pull { name, size } => commands::models_pull(name, size, &options.flags),
+            ModelsCommand::Remove { name, size } => {
+                commands::models_remove(name, size, &options.flags)
+            },
         },
+        Command::Outdated { pre } => commands::outdated(pre, &options.flags),
+        Command::Pin { names } => commands::pin(names, &options.flags),
+        Command::Profile { command } => match command {
+            ProfileCommand::List => commands::profile_list(options.profile, &options.flags),
+            ProfileCommand::Create { name } => commands::profile_create(name, &options.flags),
+            ProfileCommand::Copy { from, to } => commands::profile_copy(from, to, &options.flags),
+            ProfileCommand::Delete { name } => commands::profile_delete(name, &options.flags),
+            ProfileCommand::Use { name } => commands::profile_use(name, &options.flags),
+        },
+        Command::Reinstall {
+            names,
+            version,
+            keep_version,
+            model_size,
+        } => commands::reinstall(names, version, keep_version, model_size, &options.flags),
         Command::Resolve { url } => commands::resolve(url, &options.flags),
-        Command::Uninstall { names } => commands::uninstall(names, &options.flags),
+        Command::Rollback { name, to, list } => {
+            if list {
+                commands::rollback_list(name, &options.flags)
+            } else {
+                commands::rollback(name.unwrap(), to, &options.flags)
+            }
+        },
+        Command::SelfUpdate { check, rollback } => {
+            commands::self_update(check, rollback, &options.flags)
+        },
+        Command::Settings {
+            key,
+            value,
+            module,
+            unset,
+        } => commands::settings(key, value, module, unset, &options.flags),
+        Command::Uninstall {
+            modules,
+            purge,
+            cascade,
+            force,
+        } => commands::uninstall(modules.into(), purge, cascade, force, &options.flags),
+        Command::Unpin { names } => commands::unpin(names, &options.flags),
         Command::Upgrade {
-            names,
+            modules,
             version,
             model_size,
-        } => commands::upgrade(names, version, model_size, &options.flags),
+            check,
+            dry_run,
+            keep_versions,
+            changes,
+            ignore_pins,
+            pre,
+            jobs,
+            patch,
+            minor,
+            major,
+        } => commands::upgrade(
+            modules.into(),
+            commands::UpgradeOptions {
+                version,
+                model_size,
+                check,
+                dry_run,
+                keep_versions,
+                changes,
+                ignore_pins,
+                pre,
+                jobs,
+                policy: if patch {
+                    Some(UpgradePolicy::Patch)
+                } else if minor {
+                    Some(UpgradePolicy::Minor)
+                } else if major {
+                    Some(UpgradePolicy::Major)
+                } else {
+                    None
+                },
+            },
+            &options.flags,
+        ),
     };
 
     match result {
@@ -211,7 +844,11 @@ pub fn main() -> SysexitsError {
 }
 
 const CONFIG_USAGE: &str = r#"
-    config <module>                     # Interactive configuration
-    config <module> <key>               # Show value for key
-    config <module> [<key> <value>]...  # Set key(s) to value(s)
+    config <module>                           # Interactive configuration
+    config <module> <key>                     # Show value for key
+    config <module> [<key> <value>]...        # Set key(s) to value(s)
+    config <module> --secret <key> [<value>]  # Store key as a secret, encrypted
+    config [<module>] --list                  # List effective values
+    config export [<module>]...               # Export configuration
+    config import <file>                      # Import configuration
 "#;
@@ -0,0 +1,213 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Models required by modules, as declared in their manifests under
+//! `requires.models`. Models are downloaded into the Hugging Face cache.
+
+use asimov_module::{ModuleManifest, RequiredModel};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelFile {
+    /// The Hugging Face repository, e.g., `facebook/dinov2-base`.
+    pub repo: String,
+    /// The name of the size choice, or `None` if the model has no choices.
+    pub size: Option<String>,
+    /// The file within the repository.
+    pub filename: String,
+}
+
+impl ModelFile {
+    /// Returns the path to the file in the local cache, if downloaded.
+    pub fn local_path(&self) -> Option<PathBuf> {
+        asimov_huggingface::file_exists(&self.repo, &self.filename)
+    }
+
+    /// Returns the size of the downloaded file, if downloaded.
+    pub fn local_size(&self) -> Option<u64> {
+        self.local_path()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .map(|md| md.len())
+    }
+
+    pub fn url(&self) -> String {
+        format!(
+            "https://huggingface.co/{}/resolve/main/{}",
+            self.repo, self.filename
+        )
+    }
+
+    /// Fetches the download size of the file, if the server reports it.
+    pub async fn fetch_download_size(&self, client: &reqwest::Client) -> Option<u64> {
+        let response = client
+            .head(self.url())
+            .send()
+            .await
+            .inspect_err(|err| tracing::debug!(?err))
+            .ok()?;
+        if !response.status().is_success() {
+            tracing::debug!(url = self.url(), status = ?response.status());
+            return None;
+        }
+        response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Downloads the file into the local cache, unless already present.
+    pub fn pull(&self) -> Result<PathBuf, asimov_huggingface::HuggingfaceError> {
+        asimov_huggingface::ensure_file(&self.repo, &self.filename)
+    }
+
+    /// Removes the downloaded file from the local cache, returning the number
+    /// of bytes freed.
+    pub fn remove(&self) -> io::Result<u64> {
+        match self.local_path() {
+            Some(path) => remove_cached_file(&path),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Removes the file from the Hugging Face cache. The file is usually a link
+/// in a snapshot directory to the cache's blob storage, in which case the
+/// blob is only deleted when no other snapshot links to it. Returns the
+/// number of bytes freed.
+fn remove_cached_file(path: &Path) -> io::Result<u64> {
+    let blob = std::fs::canonicalize(path)?;
+    let size = std::fs::metadata(&blob)?.len();
+    std::fs::remove_file(path)?;
+    if blob == path {
+        return Ok(size);
+    }
+
+    // The cache is laid out as `<repo>/snapshots/<revision>/<file>` linking
+    // to `<repo>/blobs/<hash>`:
+    let Some(snapshots) = blob
+        .parent()
+        .and_then(Path::parent)
+        .map(|repo| repo.join("snapshots"))
+    else {
+        return Ok(0);
+    };
+    if links_to(&snapshots, &blob)? {
+        return Ok(0);
+    }
+    std::fs::remove_file(&blob)?;
+    Ok(size)
+}
+
+/// Returns whether any file in the directory, recursively, resolves to the
+/// target.
+fn links_to(dir: &Path, target: &Path) -> io::Result<bool> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            if links_to(&path, target)? {
+                return Ok(true);
+            }
+        } else if std::fs::canonicalize(&path).is_ok_and(|resolved| resolved == target) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Returns all the model files, for every size choice, required by the module.
+pub fn module_models(manifest: &ModuleManifest) -> Vec<ModelFile> {
+    let Some(ref requires) = manifest.requires else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for (name, model) in &requires.models {
+        let Some(repo) = name.strip_prefix("hf:") else {
+            tracing::debug!(
+                ?name,
+                "unexpected format for required model, only `hf:<user>/<repo>` is supported"
+            );
+            continue;
+        };
+
+        match model {
+            RequiredModel::Url(filename) => files.push(ModelFile {
+                repo: repo.into(),
+                size: None,
+                filename: filename.clone(),
+            }),
+            RequiredModel::Choices(choices) => {
+                files.extend(choices.iter().map(|(size, filename)| ModelFile {
+                    repo: repo.into(),
+                    size: Some(size.clone()),
+                    filename: filename.clone(),
+                }))
+            },
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_models() {
+        let manifest: ModuleManifest = serde_yml::from_str(indoc::indoc! {"
+            name: foo
+            label: Foo
+            summary: ''
+            links: []
+            requires:
+              models:
+                hf:first/model: model.bin
+                hf:second/model:
+                  small: model_small.bin
+                  large: model_large.bin
+                other:model: ignored.bin
+        "})
+        .unwrap();
+
+        let models = module_models(&manifest);
+        let sizes: Vec<_> = models.iter().map(|m| m.size.as_deref()).collect();
+        assert_eq!(sizes, vec![None, Some("small"), Some("large")]);
+        assert_eq!(models[2].repo, "second/model");
+        assert_eq!(models[2].filename, "model_large.bin");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_cached_file() {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir::TempDir::new().unwrap();
+        let blobs = dir.child("blobs");
+        std::fs::create_dir_all(&blobs).unwrap();
+        std::fs::write(blobs.join("abc"), "model").unwrap();
+        for revision in ["first", "second"] {
+            let snapshot = dir.child("snapshots").join(revision);
+            std::fs::create_dir_all(&snapshot).unwrap();
+            symlink("../../blobs/abc", snapshot.join("model.bin")).unwrap();
+        }
+
+        let first = dir.child("snapshots").join("first").join("model.bin");
+        assert_eq!(remove_cached_file(&first).unwrap(), 0);
+        assert!(!first.exists());
+        assert!(blobs.join("abc").exists());
+
+        let second = dir.child("snapshots").join("second").join("model.bin");
+        assert_eq!(remove_cached_file(&second).unwrap(), 5);
+        assert!(!blobs.join("abc").exists());
+    }
+}
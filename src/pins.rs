@@ -0,0 +1,91 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Modules pinned against upgrades, stored as one file per module under
+//! `~/.asimov/modules/pinned/`, containing the pinned version, if any.
+
+use crate::store::ModuleStore;
+use std::{io, path::PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pin {
+    pub module: String,
+    /// The version the module is held at, or `None` to hold it at whatever
+    /// version is installed.
+    pub version: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Pins {
+    store: ModuleStore,
+}
+
+impl Default for Pins {
+    fn default() -> Self {
+        Self {
+            store: ModuleStore::named("pinned"),
+        }
+    }
+}
+
+impl Pins {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            store: ModuleStore::new(dir),
+        }
+    }
+
+    pub async fn get(&self, module_name: &str) -> io::Result<Option<Pin>> {
+        let content = self.store.read(module_name).await?;
+        Ok(content.map(|content| Pin {
+            module: module_name.into(),
+            version: Some(content.trim())
+                .filter(|version| !version.is_empty())
+                .map(String::from),
+        }))
+    }
+
+    pub async fn pin(&self, module_name: &str, version: Option<&str>) -> io::Result<()> {
+        let content = version.map(|v| format!("{v}\n")).unwrap_or_default();
+        self.store.write(module_name, content).await
+    }
+
+    /// Removes the pin, returning whether the module was pinned.
+    pub async fn unpin(&self, module_name: &str) -> io::Result<bool> {
+        self.store.remove(module_name).await
+    }
+
+    pub async fn list(&self) -> io::Result<Vec<Pin>> {
+        let mut pins = Vec::new();
+        for module_name in self.store.modules().await? {
+            if let Some(pin) = self.get(&module_name).await? {
+                pins.push(pin);
+            }
+        }
+        Ok(pins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pins() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let pins = Pins::new(dir.child("pinned"));
+
+        pins.pin("foo", Some("1.2.0")).await.unwrap();
+        pins.pin("bar", None).await.unwrap();
+        assert_eq!(
+            pins.get("foo").await.unwrap().unwrap().version.as_deref(),
+            Some("1.2.0")
+        );
+        let listed: Vec<_> = pins.list().await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].module, "bar");
+        assert_eq!(listed[0].version, None);
+
+        assert!(pins.unpin("foo").await.unwrap());
+        assert_eq!(pins.get("foo").await.unwrap(), None);
+    }
+}
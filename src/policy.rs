@@ -0,0 +1,173 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Upgrade policies, limiting how far modules move on upgrade based on
+//! semantic versioning, and release channels.
+
+use derive_more::Display;
+use semver::Version;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UpgradePolicy {
+    /// Only upgrade to releases with the same major and minor version.
+    #[display("patch")]
+    Patch,
+    /// Only upgrade to releases with the same major version.
+    #[default]
+    #[display("minor")]
+    Minor,
+    /// Upgrade to any newer release.
+    #[display("major")]
+    Major,
+}
+
+impl FromStr for UpgradePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "patch" => Ok(Self::Patch),
+            "minor" => Ok(Self::Minor),
+            "major" => Ok(Self::Major),
+            _ => Err(format!("unknown upgrade policy `{s}`")),
+        }
+    }
+}
+
+impl UpgradePolicy {
+    /// Returns whether the policy allows moving from `current` to `target`.
+    /// Doesn't check that `target` is newer. As in semver, a minor bump of a
+    /// `0.x` version counts as a major upgrade.
+    pub fn allows(&self, current: &Version, target: &Version) -> bool {
+        let same_minor = current.major == target.major && current.minor == target.minor;
+        match self {
+            Self::Patch => same_minor,
+            Self::Minor if current.major == 0 => same_minor,
+            Self::Minor => current.major == target.major,
+            Self::Major => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq, Eq)]
+pub enum Channel {
+    /// Only stable releases.
+    #[default]
+    #[display("stable")]
+    Stable,
+    /// Pre-releases, e.g., `25.0.0-dev.11`, as well as stable releases.
+    #[display("dev")]
+    Dev,
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(Self::Stable),
+            "dev" => Ok(Self::Dev),
+            _ => Err(format!("unknown release channel `{s}`")),
+        }
+    }
+}
+
+impl Channel {
+    pub fn includes_pre(&self) -> bool {
+        *self == Self::Dev
+    }
+}
+
+/// Parses a release version, which may have a leading `v`.
+pub fn parse_version(version: &str) -> Option<Version> {
+    Version::parse(version.strip_prefix('v').unwrap_or(version)).ok()
+}
+
+/// Returns the newest of the `candidates` which is newer than `current` and
+/// allowed by the policy. Pre-releases are only considered if
+/// `include_pre` is set.
+pub fn select_version<'a>(
+    current: &Version,
+    candidates: impl IntoIterator<Item = &'a str>,
+    policy: UpgradePolicy,
+    include_pre: bool,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| Some((parse_version(candidate)?, candidate)))
+        .filter(|(version, _)| include_pre || version.pre.is_empty())
+        .filter(|(version, _)| version > current && policy.allows(current, version))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the newest of the `candidates` on the channel.
+pub fn newest_version<'a>(
+    candidates: impl IntoIterator<Item = &'a str>,
+    channel: Channel,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| Some((parse_version(candidate)?, candidate)))
+        .filter(|(version, _)| channel.includes_pre() || version.pre.is_empty())
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns whether the version is newer than `from` and not newer than
+/// `to`, where a missing bound is unbounded. Versions that can't be parsed
+/// are only within the range if it's unbounded.
+pub fn in_range(version: &str, from: Option<&str>, to: Option<&str>) -> bool {
+    let Some(version) = parse_version(version) else {
+        return from.is_none() && to.is_none();
+    };
+    from.and_then(parse_version)
+        .is_none_or(|from| version > from)
+        && to.and_then(parse_version).is_none_or(|to| version <= to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_version() {
+        let current = parse_version("1.2.3").unwrap();
+        let releases = [
+            "v2.0.0",
+            "1.3.1",
+            "1.3.0",
+            "1.2.4",
+            "1.2.2",
+            "1.4.0-dev.1",
+            "x",
+        ];
+
+        let select = |policy, pre| select_version(&current, releases, policy, pre);
+        assert_eq!(select(UpgradePolicy::Patch, false), Some("1.2.4"));
+        assert_eq!(select(UpgradePolicy::Minor, false), Some("1.3.1"));
+        assert_eq!(select(UpgradePolicy::Minor, true), Some("1.4.0-dev.1"));
+        assert_eq!(select(UpgradePolicy::Major, false), Some("v2.0.0"));
+
+        assert_eq!(newest_version(releases, Channel::Stable), Some("v2.0.0"));
+        assert_eq!(
+            newest_version(["1.0.0", "1.1.0-dev.2", "1.1.0-dev.10"], Channel::Dev),
+            Some("1.1.0-dev.10")
+        );
+
+        let newest = parse_version("2.0.0").unwrap();
+        assert!(in_range("1.3.0", Some("1.2.3"), Some("v1.3.0")));
+        assert!(!in_range("1.2.3", Some("1.2.3"), None));
+        assert!(!in_range("x", Some("1.2.3"), None));
+        assert_eq!(
+            select_version(&newest, releases, UpgradePolicy::Major, false),
+            None
+        );
+
+        let unstable = parse_version("0.1.2").unwrap();
+        let releases = ["0.2.0", "0.1.3"];
+        let select = |policy| select_version(&unstable, releases, policy, false);
+        assert_eq!(select(UpgradePolicy::Minor), Some("0.1.3"));
+        assert_eq!(select(UpgradePolicy::Major), Some("0.2.0"));
+    }
+}
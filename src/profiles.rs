@@ -0,0 +1,251 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Named configuration profiles, each holding its own configuration of the
+//! modules under `~/.asimov/configs/<profile>/<module>/`.
+//!
+//! Configuration directories are only accessible by the user (`0700`) and
+//! configuration files only readable by the user (`0600`).
+
+use asimov_env::paths::asimov_root;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The profile used unless another is selected.
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Clone, Debug)]
+pub struct Profiles {
+    dir: PathBuf,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self::new(asimov_root().join("configs"))
+    }
+}
+
+impl Profiles {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the directory holding the profile's configuration.
+    pub fn dir(&self, profile: &str) -> PathBuf {
+        self.dir.join(profile)
+    }
+
+    /// Returns the name of the profile to use: the given one, if any,
+    /// otherwise the one selected with [`Profiles::set_active`], otherwise
+    /// the default profile.
+    pub async fn resolve(&self, profile: Option<&str>) -> io::Result<String> {
+        if let Some(profile) = profile {
+            return Ok(profile.into());
+        }
+        Ok(self
+            .active()
+            .await?
+            .unwrap_or_else(|| DEFAULT_PROFILE.into()))
+    }
+
+    /// Returns the selected profile, if any.
+    pub async fn active(&self) -> io::Result<Option<String>> {
+        match tokio::fs::read_to_string(self.active_path()).await {
+            Ok(content) => Ok(Some(content.trim())
+                .filter(|profile| !profile.is_empty())
+                .map(String::from)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn set_active(&self, profile: &str) -> io::Result<()> {
+        write_private(&self.active_path(), format!("{profile}\n")).await
+    }
+
+    /// The file recording the selected profile. Hidden, so as not to be
+    /// mistaken for a profile.
+    fn active_path(&self) -> PathBuf {
+        self.dir.join(".profile")
+    }
+
+    pub async fn exists(&self, profile: &str) -> io::Result<bool> {
+        if profile == DEFAULT_PROFILE {
+            return Ok(true);
+        }
+        tokio::fs::try_exists(self.dir(profile)).await
+    }
+
+    /// Returns the names of all profiles, including the default one.
+    pub async fn list(&self) -> io::Result<Vec<String>> {
+        let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+        let mut read_dir = match tokio::fs::read_dir(&self.dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(profiles),
+            Err(e) => return Err(e),
+        };
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().await?.is_dir()
+                && !name.starts_with('.')
+                && name != DEFAULT_PROFILE
+            {
+                profiles.push(name);
+            }
+        }
+        profiles[1..].sort();
+        Ok(profiles)
+    }
+
+    /// Returns the directory holding all profiles.
+    pub fn root(&self) -> &Path {
+        &self.dir
+    }
+
+    pub async fn create(&self, profile: &str) -> io::Result<()> {
+        create_private_dir(&self.dir(profile)).await
+    }
+
+    /// Copies the configuration of all modules from one profile to another,
+    /// overwriting any existing values.
+    pub async fn copy(&self, from: &str, to: &str) -> io::Result<()> {
+        copy_dir(&self.dir(from), &self.dir(to)).await
+    }
+
+    pub async fn delete(&self, profile: &str) -> io::Result<()> {
+        tokio::fs::remove_dir_all(self.dir(profile)).await?;
+        if self.active().await?.as_deref() == Some(profile) {
+            tokio::fs::remove_file(self.active_path()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the name can be used for a profile.
+pub fn is_valid_name(profile: &str) -> bool {
+    !profile.is_empty()
+        && !profile.starts_with('.')
+        && profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Creates the directory and any missing parents, accessible only by the
+/// user.
+pub async fn create_private_dir(path: &Path) -> io::Result<()> {
+    let mut builder = tokio::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder.create(path).await
+}
+
+/// Writes the file readable only by the user, creating its directory if
+/// needed. The content goes to a temporary file first, which then replaces
+/// the file, so that an interrupted write doesn't leave a truncated file.
+pub async fn write_private(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    create_private_dir(dir).await?;
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = dir.join(format!(".{file_name}.{}.tmp", std::process::id()));
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let result = async {
+        let mut file = options.open(&temp_path).await?;
+        file.write_all(content.as_ref()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temp_path, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    result
+}
+
+async fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    create_private_dir(to).await?;
+    let mut read_dir = match tokio::fs::read_dir(from).await {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    while let Some(entry) = read_dir.next_entry().await? {
+        let target = to.join(entry.file_name());
+        if entry.file_type().await?.is_dir() {
+            Box::pin(copy_dir(&entry.path(), &target)).await?;
+        } else {
+            write_private(&target, tokio::fs::read(entry.path()).await?).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_profiles() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let profiles = Profiles::new(dir.child("configs"));
+
+        assert_eq!(profiles.list().await.unwrap(), ["default"]);
+        assert_eq!(profiles.resolve(None).await.unwrap(), "default");
+
+        tokio::fs::create_dir_all(profiles.dir("default").join("foo"))
+            .await
+            .unwrap();
+        tokio::fs::write(profiles.dir("default").join("foo").join("key"), "value")
+            .await
+            .unwrap();
+
+        profiles.create("staging").await.unwrap();
+        profiles.copy("default", "production").await.unwrap();
+        assert_eq!(
+            profiles.list().await.unwrap(),
+            ["default", "production", "staging"]
+        );
+        assert_eq!(
+            std::fs::read_to_string(profiles.dir("production").join("foo").join("key")).unwrap(),
+            "value"
+        );
+
+        profiles.set_active("staging").await.unwrap();
+        assert_eq!(profiles.resolve(None).await.unwrap(), "staging");
+        assert_eq!(
+            profiles.resolve(Some("production")).await.unwrap(),
+            "production"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode =
+                |path: PathBuf| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(profiles.dir("staging")), 0o700);
+            assert_eq!(mode(profiles.dir("production").join("foo")), 0o700);
+            assert_eq!(
+                mode(profiles.dir("production").join("foo").join("key")),
+                0o600
+            );
+        }
+
+        profiles.delete("staging").await.unwrap();
+        assert!(!profiles.exists("staging").await.unwrap());
+        assert_eq!(profiles.resolve(None).await.unwrap(), "default");
+
+        assert!(is_valid_name("prod-1"));
+        assert!(!is_valid_name("../etc"));
+        assert!(!is_valid_name(".profile"));
+    }
+}
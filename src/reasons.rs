@@ -0,0 +1,91 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Why modules were installed, stored as one file per module under
+//! `~/.asimov/modules/reasons/`. Modules without a recorded reason are
+//! considered installed manually.
+
+use crate::store::ModuleStore;
+use derive_more::Display;
+use std::{io, path::PathBuf, str::FromStr};
+
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq, Eq)]
+pub enum InstallReason {
+    /// The module was requested explicitly.
+    #[default]
+    #[display("manual")]
+    Manual,
+    /// The module was installed as a dependency of another module.
+    #[display("auto")]
+    Auto,
+}
+
+impl FromStr for InstallReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(Self::Manual),
+            "auto" => Ok(Self::Auto),
+            _ => Err(format!("unknown install reason `{s}`")),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InstallReasons {
+    store: ModuleStore,
+}
+
+impl Default for InstallReasons {
+    fn default() -> Self {
+        Self {
+            store: ModuleStore::named("reasons"),
+        }
+    }
+}
+
+impl InstallReasons {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            store: ModuleStore::new(dir),
+        }
+    }
+
+    pub async fn get(&self, module_name: &str) -> io::Result<InstallReason> {
+        let Some(content) = self.store.read(module_name).await? else {
+            return Ok(InstallReason::default());
+        };
+        Ok(content.trim().parse().unwrap_or_else(|e| {
+            tracing::warn!(module_name, "ignoring install reason: {e}");
+            InstallReason::default()
+        }))
+    }
+
+    pub async fn set(&self, module_name: &str, reason: InstallReason) -> io::Result<()> {
+        self.store.write(module_name, format!("{reason}\n")).await
+    }
+
+    /// Forgets the install reason of an uninstalled module.
+    pub async fn remove(&self, module_name: &str) -> io::Result<()> {
+        self.store.remove(module_name).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reasons() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let reasons = InstallReasons::new(dir.child("reasons"));
+
+        assert_eq!(reasons.get("foo").await.unwrap(), InstallReason::Manual);
+
+        reasons.set("foo", InstallReason::Auto).await.unwrap();
+        assert_eq!(reasons.get("foo").await.unwrap(), InstallReason::Auto);
+
+        reasons.remove("foo").await.unwrap();
+        assert_eq!(reasons.get("foo").await.unwrap(), InstallReason::Manual);
+    }
+}
@@ -9,10 +9,6 @@ use crate::{
     SysexitsError::{self},
     registry,
 };
-use asimov_env::{
-    env::Env,
-    envs::{PythonEnv, RubyEnv},
-};
 use derive_more::Display;
 
 #[derive(Clone, Debug)]
@@ -23,20 +19,7 @@ pub struct ModuleMetadata {
     pub url: String,
 }
 
-impl ModuleMetadata {
-    pub fn is_installed(&self) -> std::io::Result<bool> {
-        match self.r#type {
-            ModuleType::Rust => {
-                let command_name = format!("{}-module", self.name); // FIXME
-                Ok(clientele::SubcommandsProvider::find("asimov-", &command_name).is_some())
-            },
-            ModuleType::Ruby => RubyEnv::default().is_module_installed(&self.name),
-            ModuleType::Python => PythonEnv::default().is_module_installed(&self.name),
-        }
-    }
-}
-
-#[derive(Clone, Display, Debug)]
+#[derive(Clone, Copy, Display, Debug, PartialEq, Eq)]
 pub enum ModuleType {
     #[display("rust")]
     Rust,
@@ -46,7 +29,42 @@ pub enum ModuleType {
     Python,
 }
 
+impl core::str::FromStr for ModuleType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rust" => Ok(Self::Rust),
+            "ruby" => Ok(Self::Ruby),
+            "python" => Ok(Self::Python),
+            _ => Err(format!(
+                "unknown module type `{s}`, expected rust, ruby or python"
+            )),
+        }
+    }
+}
+
 impl ModuleType {
+    /// Infers the type of a module from the package indices its manifest
+    /// links to, if any.
+    pub fn from_links(links: &[impl AsRef<str>]) -> Option<Self> {
+        links.iter().find_map(|link| {
+            let host = reqwest::Url::parse(link.as_ref())
+                .ok()?
+                .host_str()?
+                .to_owned();
+            if host.ends_with("crates.io") {
+                Some(Self::Rust)
+            } else if host.ends_with("rubygems.org") {
+                Some(Self::Ruby)
+            } else if host.ends_with("pypi.org") {
+                Some(Self::Python)
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn origin(&self) -> &'static str {
         use ModuleType::*;
         match self {
@@ -57,19 +75,14 @@ impl ModuleType {
     }
 }
 
-pub fn is_enabled(_module_name: &str) -> bool {
-    true // TODO
-}
-
 pub async fn fetch_module(module_name: &str) -> Option<ModuleMetadata> {
     let modules = registry::fetch_modules().await.ok()?;
-    let module = modules.into_iter().find(|m| m.name == module_name);
-    module
+    modules.into_iter().find(|m| m.name == module_name)
 }
 
 pub async fn fetch_modules() -> Result<Vec<ModuleMetadata>, SysexitsError> {
     // Disable registry modules temporarily.
-    return Ok(Vec::new());
+    Ok(Vec::new())
 
     // // Spawn tasks to fetch module package metadata:
     // let rust_task = task::spawn(async {
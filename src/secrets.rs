@@ -0,0 +1,353 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Encryption of secret configuration variables at rest.
+//!
+//! Values are encrypted with AES-256-GCM, using either a key derived with
+//! PBKDF2 from a passphrase, in `ASIMOV_PASSPHRASE`, or the random key in a
+//! key file, in `ASIMOV_KEY_FILE` or by default `~/.asimov/secret.key`.
+//!
+//! Which variables are secret is recorded per module under
+//! `~/.asimov/modules/secrets/<module>`, one variable name per line, either
+//! from the `secret: true` hint of the variable in the module's manifest or
+//! with `config --secret`.
+
+use crate::{profiles::write_private, store::ModuleStore};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce, aead::Aead};
+use asimov_env::paths::asimov_root;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use sha2::Sha256;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The environment variable holding the passphrase.
+pub const PASSPHRASE_ENV: &str = "ASIMOV_PASSPHRASE";
+
+/// The environment variable holding the path to the key file.
+pub const KEY_FILE_ENV: &str = "ASIMOV_KEY_FILE";
+
+/// The extension of the files holding encrypted values.
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+
+/// The format version, the first byte of every encrypted value.
+const VERSION: u8 = 1;
+/// The length of the salt for deriving the key from a passphrase.
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// PBKDF2 rounds for passphrases, which may be weak.
+const PASSPHRASE_ROUNDS: u32 = 210_000;
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("no key to encrypt or decrypt secrets, set `{PASSPHRASE_ENV}` or `{KEY_FILE_ENV}`")]
+    NoKey,
+    #[error("failed to read key file `{0}`: {1}")]
+    ReadKey(PathBuf, io::Error),
+    #[error("failed to create key file `{0}`: {1}")]
+    CreateKey(PathBuf, io::Error),
+    #[error("the key file `{0}` doesn't hold a 256-bit key in hexadecimal")]
+    MalformedKey(PathBuf),
+    #[error("the encrypted value is malformed")]
+    Malformed,
+    #[error("unable to decrypt the value, the passphrase or key file is wrong")]
+    WrongKey,
+    #[error("the decrypted value isn't valid UTF-8")]
+    NotUtf8,
+}
+
+/// The passphrase that the encryption key is derived from, or the key read
+/// from the key file.
+#[derive(Clone)]
+pub enum SecretKey {
+    Passphrase(String),
+    KeyFile([u8; 32]),
+}
+
+impl core::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Passphrase(_) => f.write_str("Passphrase(..)"),
+            Self::KeyFile(_) => f.write_str("KeyFile(..)"),
+        }
+    }
+}
+
+impl SecretKey {
+    /// Returns the path of the key file, unless configured the default one.
+    pub fn key_file_path() -> PathBuf {
+        std::env::var_os(KEY_FILE_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| asimov_root().join("secret.key"))
+    }
+
+    /// Loads the passphrase or the key file, if either is present.
+    pub fn load(key_file: &Path) -> Result<Option<Self>, SecretError> {
+        if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV)
+            && !passphrase.is_empty()
+        {
+            return Ok(Some(Self::Passphrase(passphrase)));
+        }
+
+        match std::fs::read_to_string(key_file) {
+            Ok(content) => match unhex(content.trim()) {
+                Some(key) => Ok(Some(Self::KeyFile(key))),
+                None => Err(SecretError::MalformedKey(key_file.into())),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(SecretError::ReadKey(key_file.into(), e)),
+        }
+    }
+
+    /// Loads the passphrase or the key file, failing if neither is present.
+    pub fn require(key_file: &Path) -> Result<Self, SecretError> {
+        Self::load(key_file)?.ok_or(SecretError::NoKey)
+    }
+
+    /// Loads the passphrase or the key file, creating a random key file if
+    /// neither is present. Returns the key and whether the key file was
+    /// created.
+    pub async fn load_or_create(key_file: &Path) -> Result<(Self, bool), SecretError> {
+        if let Some(key) = Self::load(key_file)? {
+            return Ok((key, false));
+        }
+
+        let key: [u8; 32] = rand::random();
+        write_private(key_file, hex(&key))
+            .await
+            .map_err(|e| SecretError::CreateKey(key_file.into(), e))?;
+        Ok((Self::KeyFile(key), true))
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Aes256Gcm {
+        let key = match self {
+            Self::Passphrase(passphrase) => pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(
+                passphrase.as_bytes(),
+                salt,
+                PASSPHRASE_ROUNDS,
+            ),
+            Self::KeyFile(key) => *key,
+        };
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+    }
+
+    /// Encrypts the value, returning it encoded as Base64.
+    pub fn encrypt(&self, value: &str) -> String {
+        let salt: [u8; SALT_LEN] = rand::random();
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = self
+            .cipher(&salt)
+            .encrypt(Nonce::from_slice(&nonce), value.as_bytes())
+            .expect("encrypting in memory doesn't fail");
+
+        let mut data = Vec::with_capacity(1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+        data.push(VERSION);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        BASE64.encode(data)
+    }
+
+    /// Decrypts a value encrypted with [`SecretKey::encrypt`].
+    pub fn decrypt(&self, encoded: &str) -> Result<String, SecretError> {
+        let data = BASE64
+            .decode(encoded.trim())
+            .map_err(|_| SecretError::Malformed)?;
+        if data.len() < 1 + SALT_LEN + NONCE_LEN + TAG_LEN || data[0] != VERSION {
+            return Err(SecretError::Malformed);
+        }
+
+        let (salt, data) = data[1..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let value = self
+            .cipher(salt)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SecretError::WrongKey)?;
+        String::from_utf8(value).map_err(|_| SecretError::NotUtf8)
+    }
+}
+
+/// Masks a secret value for display, keeping only a hint of its end.
+pub fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 12 {
+        return "********".into();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("********{tail}")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(core::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// The variables of each module which are stored encrypted.
+#[derive(Clone, Debug)]
+pub struct SecretVariables {
+    store: ModuleStore,
+}
+
+impl Default for SecretVariables {
+    fn default() -> Self {
+        Self {
+            store: ModuleStore::named("secrets").private(),
+        }
+    }
+}
+
+impl SecretVariables {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            store: ModuleStore::new(dir).private(),
+        }
+    }
+
+    pub async fn get(&self, module_name: &str) -> io::Result<Vec<String>> {
+        let content = self.store.read(module_name).await?.unwrap_or_default();
+        Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    pub async fn is_secret(&self, module_name: &str, var_name: &str) -> io::Result<bool> {
+        Ok(self.get(module_name).await?.iter().any(|v| v == var_name))
+    }
+
+    /// Marks the variables of the module as secret, in addition to those
+    /// already marked.
+    pub async fn add(&self, module_name: &str, var_names: &[String]) -> io::Result<()> {
+        let mut secrets = self.get(module_name).await?;
+        let before = secrets.len();
+        for var_name in var_names {
+            if !secrets.contains(var_name) {
+                secrets.push(var_name.clone());
+            }
+        }
+        if secrets.len() == before {
+            return Ok(());
+        }
+
+        let content: String = secrets.iter().map(|v| format!("{v}\n")).collect();
+        self.store.write(module_name, content).await
+    }
+
+    /// Removes the secret marks of the module, returning whether there were
+    /// any.
+    pub async fn remove(&self, module_name: &str) -> io::Result<bool> {
+        self.store.remove(module_name).await
+    }
+}
+
+/// Returns the names of the variables marked `secret: true` in the source
+/// of a module manifest, which [`asimov_module::ModuleManifest`] doesn't
+/// retain. Variables without an environment variable are left out, as the
+/// module would have no way to receive their decrypted values.
+pub fn manifest_secrets(source: &str) -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    struct Manifest {
+        #[serde(default)]
+        config: Option<Config>,
+    }
+    #[derive(serde::Deserialize)]
+    struct Config {
+        #[serde(default)]
+        variables: Vec<Variable>,
+    }
+    #[derive(serde::Deserialize)]
+    struct Variable {
+        name: String,
+        #[serde(default, alias = "env")]
+        environment: Option<String>,
+        #[serde(default)]
+        secret: bool,
+    }
+
+    serde_yml::from_str::<Manifest>(source)
+        .inspect_err(|err| tracing::debug!(?err, "unable to read secret hints"))
+        .ok()
+        .and_then(|manifest| manifest.config)
+        .map(|config| {
+            config
+                .variables
+                .into_iter()
+                .filter(|var| var.secret)
+                .filter_map(|var| {
+                    if var.environment.is_none() {
+                        tracing::debug!(
+                            var.name,
+                            "ignoring secret hint without environment variable"
+                        );
+                        return None;
+                    }
+                    Some(var.name)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = SecretKey::KeyFile([1; 32]);
+        let encrypted = key.encrypt("sk-secret value that spans several AES blocks");
+        assert!(!encrypted.contains("secret"));
+        assert_eq!(
+            key.decrypt(&encrypted).unwrap(),
+            "sk-secret value that spans several AES blocks"
+        );
+
+        let other = SecretKey::KeyFile([2; 32]);
+        assert!(matches!(
+            other.decrypt(&encrypted),
+            Err(SecretError::WrongKey)
+        ));
+        assert!(matches!(
+            key.decrypt("not base64!"),
+            Err(SecretError::Malformed)
+        ));
+
+        let passphrase = SecretKey::Passphrase("correct horse".into());
+        let encrypted = passphrase.encrypt("sk-123");
+        assert_eq!(passphrase.decrypt(&encrypted).unwrap(), "sk-123");
+
+        assert_eq!(unhex::<2>("00ff"), Some([0, 255]));
+        assert_eq!(unhex::<2>("00f"), None);
+        assert_eq!(unhex::<2>("00fg"), None);
+    }
+
+    #[tokio::test]
+    async fn test_secret_variables() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let secrets = SecretVariables::new(dir.child("secrets"));
+
+        assert!(!secrets.is_secret("foo", "api_key").await.unwrap());
+        secrets.add("foo", &["api_key".into()]).await.unwrap();
+        secrets.add("foo", &["api_key".into()]).await.unwrap();
+        assert_eq!(secrets.get("foo").await.unwrap(), ["api_key"]);
+        assert!(secrets.is_secret("foo", "api_key").await.unwrap());
+
+        let source = "name: foo\nconfig:\n  variables:\n    - name: api_key\n      env: FOO_API_KEY\n      secret: true\n    - name: token\n      secret: true\n    - name: model\n";
+        assert_eq!(manifest_secrets(source), ["api_key"]);
+    }
+}
@@ -0,0 +1,237 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Selecting modules by name, by shell-style pattern (e.g., `'openai*'` or
+//! `'rdf?'`), by type, or all of them.
+
+use crate::{
+    SysexitsError::{self, *},
+    installer::Installer,
+    registry::ModuleType,
+};
+use color_print::ceprintln;
+use futures::StreamExt;
+
+/// The number of manifest lookups done at the same time when selecting
+/// catalog modules by type.
+const LOOKUP_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    /// Module names and patterns.
+    pub names: Vec<String>,
+    /// Select all candidate modules.
+    pub all: bool,
+    /// Only select modules of this type.
+    pub module_type: Option<ModuleType>,
+    /// Don't print the selected modules, e.g., for machine-readable output.
+    pub quiet: bool,
+}
+
+impl Selection {
+    pub fn names(names: Vec<String>) -> Self {
+        Self {
+            names,
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the selection is anything but literal names.
+    pub fn is_expanding(&self) -> bool {
+        self.all || self.module_type.is_some() || self.names.iter().any(|name| is_pattern(name))
+    }
+
+    /// Resolves the selection against the installed modules, printing the
+    /// selected modules if anything was expanded.
+    pub async fn resolve_installed(
+        &self,
+        registry: &asimov_registry::Registry,
+    ) -> Result<Vec<String>, SysexitsError> {
+        if !self.is_expanding() {
+            return Ok(self.names.clone());
+        }
+
+        let candidates: Vec<_> = registry
+            .installed_modules()
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to read installed modules: {e}");
+                EX_UNAVAILABLE
+            })?
+            .into_iter()
+            .map(|manifest| {
+                let module_type = ModuleType::from_links(&manifest.manifest.links);
+                (manifest.manifest.name, module_type)
+            })
+            .collect();
+
+        self.resolve(&candidates)
+    }
+
+    /// Resolves the selection against the catalog of all available modules,
+    /// printing the selected modules if anything was expanded.
+    pub async fn resolve_catalog(
+        &self,
+        installer: &Installer,
+    ) -> Result<Vec<String>, SysexitsError> {
+        if !self.is_expanding() {
+            return Ok(self.names.clone());
+        }
+
+        let module_names = crate::commands::fetch_all_module_names()
+            .await
+            .map_err(|e| {
+                tracing::error!("unable to fetch list of all modules: {e}");
+                EX_UNAVAILABLE
+            })?;
+
+        // The catalog only lists names, so the types of the modules come
+        // from their latest published manifests:
+        let candidates = match self.module_type {
+            None => module_names.into_iter().map(|name| (name, None)).collect(),
+            Some(_) => {
+                futures::stream::iter(
+                    self.select(
+                        &module_names
+                            .into_iter()
+                            .map(|name| (name, None))
+                            .collect::<Vec<_>>(),
+                        false,
+                    ),
+                )
+                .map(|module_name| async move {
+                    let module_type = catalog_module_type(installer, &module_name).await;
+                    (module_name, module_type)
+                })
+                .buffered(LOOKUP_CONCURRENCY)
+                .collect::<Vec<_>>()
+                .await
+            },
+        };
+
+        self.resolve(&candidates)
+    }
+
+    fn resolve(
+        &self,
+        candidates: &[(String, Option<ModuleType>)],
+    ) -> Result<Vec<String>, SysexitsError> {
+        for name in &self.names {
+            if is_pattern(name) && glob::Pattern::new(name).is_err() {
+                ceprintln!("<s,r>error:</> invalid module pattern: `{name}`");
+                return Err(EX_USAGE);
+            }
+        }
+
+        let mut candidates = candidates.to_vec();
+        candidates.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let selected = self.select(&candidates, true);
+        if selected.is_empty() {
+            ceprintln!("<s,r>error:</> no modules match the selection.");
+            return Err(EX_USAGE);
+        }
+
+        if !self.quiet {
+            ceprintln!("Selected modules: <s>{}</>", selected.join(", "));
+        }
+        Ok(selected)
+    }
+
+    /// Selects the candidates matching the selection, keeping literal names
+    /// even if they aren't candidates, unless filtering by type.
+    fn select(&self, candidates: &[(String, Option<ModuleType>)], by_type: bool) -> Vec<String> {
+        let type_matches = |module_type: &Option<ModuleType>| {
+            !by_type || self.module_type.is_none() || *module_type == self.module_type
+        };
+
+        let mut selected: Vec<String> = Vec::new();
+        let mut push = |name: &str| {
+            if !selected.iter().any(|s| s == name) {
+                selected.push(name.into());
+            }
+        };
+
+        if self.all || self.names.is_empty() {
+            for (name, module_type) in candidates {
+                if type_matches(module_type) {
+                    push(name);
+                }
+            }
+            return selected;
+        }
+
+        for name in &self.names {
+            if is_pattern(name) {
+                let Ok(pattern) = glob::Pattern::new(name) else {
+                    continue;
+                };
+                for (candidate, module_type) in candidates {
+                    if pattern.matches(candidate) && type_matches(module_type) {
+                        push(candidate);
+                    }
+                }
+            } else {
+                match candidates.iter().find(|(candidate, _)| candidate == name) {
+                    Some((_, module_type)) if !type_matches(module_type) => (),
+                    None if by_type && self.module_type.is_some() => (),
+                    _ => push(name),
+                }
+            }
+        }
+        selected
+    }
+}
+
+/// Returns whether the name is a shell-style pattern rather than a name.
+pub fn is_pattern(name: &str) -> bool {
+    name.contains(['*', '?', '['])
+}
+
+async fn catalog_module_type(installer: &Installer, module_name: &str) -> Option<ModuleType> {
+    let version = installer
+        .fetch_latest_release(module_name)
+        .await
+        .inspect_err(|e| tracing::debug!(module_name, ?e, "unable to find latest release"))
+        .ok()?;
+    let manifest = installer
+        .fetch_manifest(module_name, &version)
+        .await
+        .inspect_err(|e| tracing::debug!(module_name, ?e, "unable to fetch manifest"))
+        .ok()?;
+    ModuleType::from_links(&manifest.links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let candidates = [
+            ("openai".to_string(), Some(ModuleType::Python)),
+            ("openai-chat".to_string(), Some(ModuleType::Rust)),
+            ("rdfs".to_string(), Some(ModuleType::Rust)),
+            ("rdf".to_string(), None),
+        ];
+
+        let selection = Selection::names(vec!["openai*".into(), "rdf?".into(), "foo".into()]);
+        assert_eq!(
+            selection.select(&candidates, true),
+            ["openai", "openai-chat", "rdfs", "foo"]
+        );
+
+        let selection = Selection {
+            module_type: Some(ModuleType::Rust),
+            ..Default::default()
+        };
+        assert_eq!(selection.select(&candidates, true), ["openai-chat", "rdfs"]);
+
+        let selection = Selection {
+            names: vec!["openai*".into(), "foo".into()],
+            module_type: Some(ModuleType::Python),
+            ..Default::default()
+        };
+        assert_eq!(selection.select(&candidates, true), ["openai"]);
+        assert!(selection.is_expanding());
+        assert!(!Selection::names(vec!["foo".into()]).is_expanding());
+    }
+}
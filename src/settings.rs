@@ -0,0 +1,193 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Settings of the module CLI itself, stored as one file per setting under
+//! `~/.asimov/modules/settings/`, with per-module overrides under
+//! `~/.asimov/modules/settings/modules/<module>/`.
+
+use crate::{policy::Channel, store::ModuleStore};
+use asimov_env::paths::asimov_root;
+use std::{io, path::PathBuf};
+
+/// The known settings, along with their allowed values.
+pub const KNOWN_SETTINGS: &[(&str, &[&str])] = &[
+    ("channel", &["stable", "dev"]),
+    ("upgrade-policy", &["patch", "minor", "major"]),
+];
+
+/// Returns the allowed values of the setting, or `None` if the setting is
+/// unknown.
+pub fn allowed_values(key: &str) -> Option<&'static [&'static str]> {
+    KNOWN_SETTINGS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, values)| *values)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Setting {
+    /// The module the setting applies to, or `None` for a global setting.
+    pub module: Option<String>,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    dir: PathBuf,
+    modules: ModuleStore,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new(asimov_root().join("modules").join("settings"))
+    }
+}
+
+impl Settings {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            modules: ModuleStore::new(dir.join("modules")),
+            dir,
+        }
+    }
+
+    fn path(&self, module_name: Option<&str>, key: &str) -> io::Result<PathBuf> {
+        Ok(match module_name {
+            Some(module_name) => self.modules.path(module_name)?.join(key),
+            None => self.dir.join(key),
+        })
+    }
+
+    pub async fn get(&self, module_name: Option<&str>, key: &str) -> io::Result<Option<String>> {
+        match tokio::fs::read_to_string(self.path(module_name, key)?).await {
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the module's own setting if there is one, and otherwise the
+    /// global setting.
+    pub async fn effective(&self, module_name: &str, key: &str) -> io::Result<Option<String>> {
+        match self.get(Some(module_name), key).await? {
+            Some(value) => Ok(Some(value)),
+            None => self.get(None, key).await,
+        }
+    }
+
+    /// Returns the release channel configured for the module, or the
+    /// default channel.
+    pub async fn channel(&self, module_name: &str) -> io::Result<Channel> {
+        let value = self.effective(module_name, "channel").await?;
+        Ok(value
+            .and_then(|value| {
+                value
+                    .parse()
+                    .inspect_err(|err| tracing::warn!(module_name, "ignoring setting: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default())
+    }
+
+    pub async fn set(&self, module_name: Option<&str>, key: &str, value: &str) -> io::Result<()> {
+        let path = self.path(module_name, key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, format!("{value}\n")).await
+    }
+
+    /// Removes the setting, returning whether it was set.
+    pub async fn unset(&self, module_name: Option<&str>, key: &str) -> io::Result<bool> {
+        match tokio::fs::remove_file(self.path(module_name, key)?).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Removes all the module's own settings, returning the path of the
+    /// removed directory, if any.
+    pub async fn remove_module(&self, module_name: &str) -> io::Result<Option<PathBuf>> {
+        let path = self.modules.path(module_name)?;
+        Ok(self.modules.remove(module_name).await?.then_some(path))
+    }
+
+    /// Returns all the settings, global ones first.
+    pub async fn list(&self) -> io::Result<Vec<Setting>> {
+        let mut settings = Vec::new();
+        for (key, _) in KNOWN_SETTINGS {
+            if let Some(value) = self.get(None, key).await? {
+                settings.push(Setting {
+                    module: None,
+                    key: key.to_string(),
+                    value,
+                });
+            }
+        }
+
+        for module_name in self.modules.modules().await? {
+            for (key, _) in KNOWN_SETTINGS {
+                if let Some(value) = self.get(Some(&module_name), key).await? {
+                    settings.push(Setting {
+                        module: Some(module_name.clone()),
+                        key: key.to_string(),
+                        value,
+                    });
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_effective() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let settings = Settings::new(dir.path());
+
+        assert_eq!(
+            settings.effective("foo", "upgrade-policy").await.unwrap(),
+            None
+        );
+
+        settings.set(None, "upgrade-policy", "patch").await.unwrap();
+        settings
+            .set(Some("foo"), "upgrade-policy", "major")
+            .await
+            .unwrap();
+        assert_eq!(
+            settings
+                .effective("foo", "upgrade-policy")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("major")
+        );
+        assert_eq!(
+            settings
+                .effective("bar", "upgrade-policy")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("patch")
+        );
+        assert_eq!(settings.list().await.unwrap().len(), 2);
+
+        assert!(settings.unset(Some("foo"), "upgrade-policy").await.unwrap());
+        assert_eq!(
+            settings
+                .effective("foo", "upgrade-policy")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("patch")
+        );
+    }
+}
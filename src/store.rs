@@ -0,0 +1,132 @@
+// This is free and unencumbered software released into the public domain.
+
+//! A store of one file per module under `~/.asimov/modules/<store>/`, as
+//! used for pins, install reasons, settings, and secret variable marks.
+
+use crate::profiles::{is_valid_name, write_private};
+use asimov_env::paths::asimov_root;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug)]
+pub struct ModuleStore {
+    dir: PathBuf,
+    private: bool,
+}
+
+impl ModuleStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            private: false,
+        }
+    }
+
+    /// Returns the store at `~/.asimov/modules/<name>/`.
+    pub fn named(name: &str) -> Self {
+        Self::new(asimov_root().join("modules").join(name))
+    }
+
+    /// Makes the written files accessible only by the user.
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path for the module, refusing names which would point
+    /// outside of the store, such as `../foo` or `foo/bar`.
+    pub fn path(&self, module_name: &str) -> io::Result<PathBuf> {
+        if !is_valid_name(module_name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid module name `{module_name}`"),
+            ));
+        }
+        Ok(self.dir.join(module_name))
+    }
+
+    pub async fn read(&self, module_name: &str) -> io::Result<Option<String>> {
+        match tokio::fs::read_to_string(self.path(module_name)?).await {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn write(&self, module_name: &str, content: impl AsRef<[u8]>) -> io::Result<()> {
+        let path = self.path(module_name)?;
+        if self.private {
+            return write_private(&path, content).await;
+        }
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(path, content).await
+    }
+
+    /// Removes the module's file, or directory, returning whether there was
+    /// one.
+    pub async fn remove(&self, module_name: &str) -> io::Result<bool> {
+        let path = self.path(module_name)?;
+        let result = match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_dir() => tokio::fs::remove_dir_all(&path).await,
+            Ok(_) => tokio::fs::remove_file(&path).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the names of the modules in the store, sorted.
+    pub async fn modules(&self) -> io::Result<Vec<String>> {
+        let mut read_dir = match tokio::fs::read_dir(&self.dir).await {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut module_names = Vec::new();
+        while let Some(entry) = read_dir.next_entry().await? {
+            let module_name = entry.file_name().to_string_lossy().into_owned();
+            if is_valid_name(&module_name) {
+                module_names.push(module_name);
+            }
+        }
+        module_names.sort();
+        Ok(module_names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_module_store() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let store = ModuleStore::new(dir.child("store"));
+
+        assert_eq!(store.read("foo").await.unwrap(), None);
+        assert!(store.modules().await.unwrap().is_empty());
+
+        store.write("foo", "1\n").await.unwrap();
+        store.write("bar", "2\n").await.unwrap();
+        assert_eq!(store.read("foo").await.unwrap().as_deref(), Some("1\n"));
+        assert_eq!(store.modules().await.unwrap(), ["bar", "foo"]);
+
+        assert!(store.remove("foo").await.unwrap());
+        assert!(!store.remove("foo").await.unwrap());
+
+        for module_name in ["", "..", "../foo", "foo/bar"] {
+            let err = store.write(module_name, "").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+// This is free and unencumbered software released into the public domain.
+
+//! The configured values of module configuration variables, stored under
+//! `~/.asimov/configs/<profile>/<module>/<variable>`, or encrypted in
+//! `<variable>.enc` for secret variables.
+
+use crate::{
+    profiles::{Profiles, write_private},
+    secrets::{ENCRYPTED_EXTENSION, SecretError, SecretKey, SecretVariables},
+};
+use asimov_module::ConfigurationVariable;
+use std::{io, path::PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum VariableError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Secret(#[from] SecretError),
+}
+
+/// Where the effective value of a variable comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VariableSource {
+    Environment,
+    File,
+    Default,
+    Unset,
+}
+
+impl core::fmt::Display for VariableSource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Environment => "env",
+            Self::File => "file",
+            Self::Default => "default",
+            Self::Unset => "unset",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Variables {
+    profiles: Profiles,
+    secrets: SecretVariables,
+    key_file: PathBuf,
+}
+
+impl Default for Variables {
+    fn default() -> Self {
+        Self::new(
+            Profiles::default(),
+            SecretVariables::default(),
+            SecretKey::key_file_path(),
+        )
+    }
+}
+
+impl Variables {
+    pub fn new(profiles: Profiles, secrets: SecretVariables, key_file: impl Into<PathBuf>) -> Self {
+        Self {
+            profiles,
+            secrets,
+            key_file: key_file.into(),
+        }
+    }
+
+    pub fn secrets(&self) -> &SecretVariables {
+        &self.secrets
+    }
+
+    fn path(&self, profile: &str, module_name: &str, var_name: &str) -> PathBuf {
+        self.profiles.dir(profile).join(module_name).join(var_name)
+    }
+
+    fn encrypted_path(&self, profile: &str, module_name: &str, var_name: &str) -> PathBuf {
+        self.path(profile, module_name, var_name)
+            .with_added_extension(ENCRYPTED_EXTENSION)
+    }
+
+    /// Returns whether the variable has a value stored in the profile,
+    /// encrypted or not.
+    pub async fn is_stored(
+        &self,
+        profile: &str,
+        module_name: &str,
+        var_name: &str,
+    ) -> io::Result<bool> {
+        Ok(
+            tokio::fs::try_exists(self.encrypted_path(profile, module_name, var_name)).await?
+                || tokio::fs::try_exists(self.path(profile, module_name, var_name)).await?,
+        )
+    }
+
+    /// Returns whether the variable's value is stored encrypted in the
+    /// profile, so that modules can't read it themselves.
+    pub async fn is_encrypted(
+        &self,
+        profile: &str,
+        module_name: &str,
+        var_name: &str,
+    ) -> io::Result<bool> {
+        tokio::fs::try_exists(self.encrypted_path(profile, module_name, var_name)).await
+    }
+
+    /// Returns the value stored in the profile, if any, decrypting it if
+    /// it's encrypted.
+    pub async fn read_stored(
+        &self,
+        profile: &str,
+        module_name: &str,
+        var_name: &str,
+    ) -> Result<Option<String>, VariableError> {
+        if let Some(encrypted) =
+            read_optional(self.encrypted_path(profile, module_name, var_name)).await?
+        {
+            return Ok(Some(
+                SecretKey::require(&self.key_file)?.decrypt(&encrypted)?,
+            ));
+        }
+        Ok(read_optional(self.path(profile, module_name, var_name))
+            .await?
+            .map(|value| value.trim().to_string()))
+    }
+
+    /// Returns the effective value of the variable along with its source:
+    /// its environment variable, the value stored in the profile, or its
+    /// default value.
+    pub async fn read(
+        &self,
+        profile: &str,
+        module_name: &str,
+        var: &ConfigurationVariable,
+    ) -> Result<(Option<String>, VariableSource), VariableError> {
+        if let Some(value) = var
+            .environment
+            .as_deref()
+            .and_then(|env_name| std::env::var(env_name).ok())
+        {
+            return Ok((Some(value), VariableSource::Environment));
+        }
+        if let Some(value) = self.read_stored(profile, module_name, &var.name).await? {
+            return Ok((Some(value), VariableSource::File));
+        }
+        match &var.default_value {
+            Some(value) => Ok((Some(value.clone()), VariableSource::Default)),
+            None => Ok((None, VariableSource::Unset)),
+        }
+    }
+
+    /// Stores the value in the profile, encrypted if the variable is secret.
+    /// Returns the path of the key file if one had to be created.
+    pub async fn write(
+        &self,
+        profile: &str,
+        module_name: &str,
+        var_name: &str,
+        value: &str,
+    ) -> Result<Option<PathBuf>, VariableError> {
+        let plain_path = self.path(profile, module_name, var_name);
+        let encrypted_path = self.encrypted_path(profile, module_name, var_name);
+        if !self.secrets.is_secret(module_name, var_name).await? {
+            write_private(&plain_path, value).await?;
+            remove_optional(encrypted_path).await?;
+            return Ok(None);
+        }
+
+        let (key, created_key) = SecretKey::load_or_create(&self.key_file).await?;
+        write_private(&encrypted_path, key.encrypt(value)).await?;
+        remove_optional(plain_path).await?;
+        Ok(created_key.then(|| self.key_file.clone()))
+    }
+
+    /// Marks the variables of the module as secret, encrypting the values
+    /// already stored in plain text in every profile. Returns the path of
+    /// the key file if one had to be created.
+    pub async fn mark_secret(
+        &self,
+        module_name: &str,
+        var_names: &[String],
+    ) -> Result<Option<PathBuf>, VariableError> {
+        self.secrets.add(module_name, var_names).await?;
+
+        let mut created_key = None;
+        for profile in self.profiles.list().await? {
+            for var_name in var_names {
+                let created = self.encrypt_stored(&profile, module_name, var_name).await?;
+                created_key = created_key.or(created);
+            }
+        }
+        Ok(created_key)
+    }
+
+    /// Encrypts the plaintext value stored in the profile, if any, after
+    /// the variable has been marked as secret. Returns the path of the key
+    /// file if one had to be created.
+    async fn encrypt_stored(
+        &self,
+        profile: &str,
+        module_name: &str,
+        var_name: &str,
+    ) -> Result<Option<PathBuf>, VariableError> {
+        match read_optional(self.path(profile, module_name, var_name)).await? {
+            Some(value) => {
+                self.write(profile, module_name, var_name, value.trim())
+                    .await
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub async fn remove(&self, profile: &str, module_name: &str, var_name: &str) -> io::Result<()> {
+        remove_optional(self.path(profile, module_name, var_name)).await?;
+        remove_optional(self.encrypted_path(profile, module_name, var_name)).await
+    }
+}
+
+async fn read_optional(path: PathBuf) -> io::Result<Option<String>> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+async fn remove_optional(path: PathBuf) -> io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_secret_variables() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let key_file = dir.child("secret.key");
+        let variables = Variables::new(
+            Profiles::new(dir.child("configs")),
+            SecretVariables::new(dir.child("secrets")),
+            &key_file,
+        );
+
+        variables
+            .write("default", "foo", "model", "gpt")
+            .await
+            .unwrap();
+        variables
+            .write("prod", "foo", "api_key", "sk-456")
+            .await
+            .unwrap();
+        let created = variables
+            .mark_secret("foo", &["api_key".into()])
+            .await
+            .unwrap();
+        assert_eq!(created, Some(key_file));
+        assert!(
+            variables
+                .is_encrypted("prod", "foo", "api_key")
+                .await
+                .unwrap()
+        );
+        let created = variables
+            .write("default", "foo", "api_key", "sk-123")
+            .await
+            .unwrap();
+        assert_eq!(created, None);
+
+        let stored = dir.child("configs").join("default").join("foo");
+        assert!(!stored.join("api_key").exists());
+        assert!(
+            !std::fs::read_to_string(stored.join("api_key.enc"))
+                .unwrap()
+                .contains("sk-123")
+        );
+
+        let var = ConfigurationVariable {
+            name: "api_key".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            variables.read("default", "foo", &var).await.unwrap(),
+            (Some("sk-123".into()), VariableSource::File)
+        );
+        assert!(
+            variables
+                .is_stored("default", "foo", "api_key")
+                .await
+                .unwrap()
+        );
+        assert!(
+            variables
+                .is_encrypted("default", "foo", "api_key")
+                .await
+                .unwrap()
+        );
+
+        variables.remove("default", "foo", "api_key").await.unwrap();
+        assert_eq!(
+            variables.read("default", "foo", &var).await.unwrap(),
+            (None, VariableSource::Unset)
+        );
+    }
+}